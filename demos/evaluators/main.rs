@@ -0,0 +1,59 @@
+use std::time::{Duration, Instant};
+
+use engine_base::{
+    operators::{add, input},
+    waiting::Waiting,
+    Emit, Engine,
+};
+use simple_engine::{EngineConfig, Evaluator, SyncEngine};
+
+const UPDATES: u32 = 20_000;
+
+// A chain of `depth` adds over one input. With `leaves`, every link of the chain also feeds a
+// listened node that nothing else depends on, doubling the nodes each update touches.
+fn run(evaluator: Evaluator, depth: usize, leaves: bool) -> Duration {
+    let engine = SyncEngine::with_config(EngineConfig {
+        evaluator,
+        ..Default::default()
+    });
+    let (input_ref, source) = input::<u64>();
+    let mut chain = source.clone();
+    let mut leaf_listeners = Vec::new();
+    for _ in 0..depth {
+        if leaves {
+            leaf_listeners.push(engine.listen(add(source.clone(), chain.clone())).wait());
+        }
+        chain = add(chain, source.clone());
+    }
+    let listener = engine.listen(chain).wait();
+    let emitter = engine.emit::<u64>(input_ref).wait();
+    engine.start().wait().expect("Engine failed to start");
+
+    let started = Instant::now();
+    for value in 0..UPDATES {
+        emitter
+            .send(u64::from(value))
+            .expect("Engine stopped early");
+        listener.recv().expect("Engine stopped early");
+    }
+    let elapsed = started.elapsed();
+    for leaf in leaf_listeners {
+        assert_eq!(leaf.try_iter().count(), UPDATES as usize);
+    }
+    engine.shutdown().wait();
+    elapsed
+}
+
+pub fn main() {
+    println!("graph\tevaluator\tupdates_per_second");
+    for (depth, leaves) in [(1, false), (64, false), (64, true), (512, true)] {
+        for evaluator in [Evaluator::Interpreted, Evaluator::Compiled] {
+            let elapsed = run(evaluator, depth, leaves);
+            let graph = if leaves { "comb" } else { "chain" };
+            println!(
+                "{graph}({depth})\t{evaluator:?}\t{:.0}",
+                f64::from(UPDATES) / elapsed.as_secs_f64()
+            );
+        }
+    }
+}
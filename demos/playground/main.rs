@@ -3,7 +3,7 @@ use std::thread;
 use engine_base::{
     operators::input,
     waiting::{MaybeWaiting, Waiting},
-    Engine,
+    Emit, Engine,
 };
 use simple_engine::SimpleEngine;
 
@@ -21,7 +21,7 @@ pub fn main() {
 
     thread::sleep(std::time::Duration::from_secs(1));
     emitter.send(42).unwrap();
-    engine.start().immediate();
+    engine.start().immediate().unwrap();
     join_handle.join().unwrap();
     engine.shutdown().wait();
 }
@@ -0,0 +1,226 @@
+use std::fmt::Write;
+
+use crate::operators::{
+    types::{Type, Wrapper},
+    window::Aggregate,
+    Desc, InputRef,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NodeKind {
+    Input(InputRef),
+    Add,
+    SaturatingAdd,
+    CheckedAdd,
+    Optional,
+    UnwrapOr,
+    MapErr,
+    Recover,
+    Errors,
+    OkOr,
+    Switch,
+    Filter,
+    Map,
+    GroupBy,
+    Count,
+    Sum,
+    Window(Aggregate),
+    LastN,
+    Hold,
+    Changes,
+    Sample,
+}
+
+impl From<&Desc> for NodeKind {
+    fn from(desc: &Desc) -> Self {
+        match desc {
+            Desc::Input(input) => NodeKind::Input(*input),
+            Desc::Add(..) => NodeKind::Add,
+            Desc::SaturatingAdd(..) => NodeKind::SaturatingAdd,
+            Desc::CheckedAdd(..) => NodeKind::CheckedAdd,
+            Desc::Optional(_) => NodeKind::Optional,
+            Desc::UnwrapOr(..) => NodeKind::UnwrapOr,
+            Desc::MapErr(..) => NodeKind::MapErr,
+            Desc::Recover(..) => NodeKind::Recover,
+            Desc::Errors(_) => NodeKind::Errors,
+            Desc::OkOr(..) => NodeKind::OkOr,
+            Desc::Switch(_) => NodeKind::Switch,
+            Desc::Filter(..) => NodeKind::Filter,
+            Desc::Map(..) => NodeKind::Map,
+            Desc::GroupBy(..) => NodeKind::GroupBy,
+            Desc::Count(_) => NodeKind::Count,
+            Desc::Sum(_) => NodeKind::Sum,
+            Desc::Window(_, aggregate, _) => NodeKind::Window(*aggregate),
+            Desc::LastN(..) => NodeKind::LastN,
+            Desc::Hold(..) => NodeKind::Hold,
+            Desc::Changes(_) => NodeKind::Changes,
+            Desc::Sample(..) => NodeKind::Sample,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NodeDescription {
+    pub id: usize,
+    pub kind: NodeKind,
+    pub rtype: Type,
+    pub dependencies: Vec<usize>,
+    pub listeners: usize,
+    pub emitters: usize,
+    pub value: Wrapper,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GraphDescription {
+    pub nodes: Vec<NodeDescription>,
+}
+
+impl GraphDescription {
+    pub fn edges(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.nodes.iter().flat_map(|node| {
+            node.dependencies
+                .iter()
+                .map(move |dependency| (*dependency, node.id))
+        })
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph engine {\n");
+        for node in &self.nodes {
+            let label = format!(
+                "{}\\n{:?} = {}\\nlisteners: {}, emitters: {}",
+                escape(&kind_label(&node.kind)),
+                node.rtype,
+                escape(&value_label(&node.value)),
+                node.listeners,
+                node.emitters,
+            );
+            writeln!(out, "    n{} [label=\"{label}\"];", node.id).unwrap();
+        }
+        for (from, to) in self.edges() {
+            writeln!(out, "    n{from} -> n{to};").unwrap();
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{\"nodes\":[");
+        for (i, node) in self.nodes.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            let (kind, input) = match &node.kind {
+                NodeKind::Input(input) => ("input", Some(input_json(*input))),
+                NodeKind::Add => ("add", None),
+                NodeKind::SaturatingAdd => ("saturating_add", None),
+                NodeKind::CheckedAdd => ("checked_add", None),
+                NodeKind::Optional => ("optional", None),
+                NodeKind::UnwrapOr => ("unwrap_or", None),
+                NodeKind::MapErr => ("map_err", None),
+                NodeKind::Recover => ("recover", None),
+                NodeKind::Errors => ("errors", None),
+                NodeKind::OkOr => ("ok_or", None),
+                NodeKind::Switch => ("switch", None),
+                NodeKind::Filter => ("filter", None),
+                NodeKind::Map => ("map", None),
+                NodeKind::GroupBy => ("group_by", None),
+                NodeKind::Count => ("count", None),
+                NodeKind::Sum => ("sum", None),
+                NodeKind::Window(Aggregate::Sum) => ("window_sum", None),
+                NodeKind::Window(Aggregate::Avg) => ("window_avg", None),
+                NodeKind::Window(Aggregate::Count) => ("window_count", None),
+                NodeKind::LastN => ("last_n", None),
+                NodeKind::Hold => ("hold", None),
+                NodeKind::Changes => ("changes", None),
+                NodeKind::Sample => ("snapshot", None),
+            };
+            write!(out, "{{\"id\":{},\"kind\":\"{kind}\"", node.id).unwrap();
+            if let Some(input) = input {
+                write!(out, ",\"input\":{input}").unwrap();
+            }
+            let dependencies = node
+                .dependencies
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(",");
+            write!(
+                out,
+                ",\"type\":\"{:?}\",\"dependencies\":[{dependencies}],\"listeners\":{},\"emitters\":{},\"value\":{}}}",
+                node.rtype,
+                node.listeners,
+                node.emitters,
+                value_label(&node.value),
+            )
+            .unwrap();
+        }
+        out.push_str("]}");
+        out
+    }
+}
+
+fn kind_label(kind: &NodeKind) -> String {
+    match kind {
+        NodeKind::Input(input) => match input.name() {
+            Some(name) => format!("input {name:?}"),
+            None => format!("input #{}", input.raw()),
+        },
+        NodeKind::Add => "add".to_string(),
+        NodeKind::SaturatingAdd => "saturating_add".to_string(),
+        NodeKind::CheckedAdd => "checked_add".to_string(),
+        NodeKind::Optional => "optional".to_string(),
+        NodeKind::UnwrapOr => "unwrap_or".to_string(),
+        NodeKind::MapErr => "map_err".to_string(),
+        NodeKind::Recover => "recover".to_string(),
+        NodeKind::Errors => "errors".to_string(),
+        NodeKind::OkOr => "ok_or".to_string(),
+        NodeKind::Switch => "switch".to_string(),
+        NodeKind::Filter => "filter".to_string(),
+        NodeKind::Map => "map".to_string(),
+        NodeKind::GroupBy => "group_by".to_string(),
+        NodeKind::Count => "count".to_string(),
+        NodeKind::Sum => "sum".to_string(),
+        NodeKind::Window(aggregate) => format!("window_{aggregate}"),
+        NodeKind::LastN => "last_n".to_string(),
+        NodeKind::Hold => "hold".to_string(),
+        NodeKind::Changes => "changes".to_string(),
+        NodeKind::Sample => "snapshot".to_string(),
+    }
+}
+
+fn input_json(input: InputRef) -> String {
+    match input.name() {
+        Some(name) => format!("\"{}\"", escape(&name)),
+        None => input.raw().to_string(),
+    }
+}
+
+fn value_label(value: &Wrapper) -> String {
+    match value {
+        Wrapper::U64(value) => value.to_string(),
+        Wrapper::Str(value) => format!("\"{}\"", escape(value)),
+        Wrapper::Overflow(overflow) => format!(
+            "{{\"left\":{},\"right\":{}}}",
+            overflow.left, overflow.right
+        ),
+        Wrapper::Custom(_) | Wrapper::Missing => "null".to_string(),
+        Wrapper::Err(error) => format!("{{\"err\":{}}}", value_label(error)),
+        Wrapper::Signal(signal) => format!("\"{}\"", escape(&signal.desc.to_string())),
+        Wrapper::Map(collection) => format!("{{\"entries\":{}}}", collection.len()),
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if c.is_control() => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out
+}
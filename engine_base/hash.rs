@@ -1,10 +1,11 @@
 use rustc_hash::FxBuildHasher;
 use std::{
+    fmt::{self, Debug, Formatter},
     hash::{BuildHasher, Hash, Hasher},
     ops::{Deref, DerefMut},
 };
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct Prehashed<T> {
     inner: T,
     hash: u64,
@@ -20,6 +21,12 @@ where
     }
 }
 
+impl<T: Debug> Debug for Prehashed<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
 impl<T> Deref for Prehashed<T> {
     type Target = T;
 
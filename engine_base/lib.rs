@@ -1,14 +1,79 @@
-use crossbeam_channel::{Receiver, Sender};
-use operators::{types::RType, InputRef, Signal};
+use crossbeam_channel::{Receiver, SendError, Sender};
+use describe::GraphDescription;
+use lifecycle::{Lifecycle, LifecycleError};
+use operators::{types::RType, Event, InputRef, Signal};
+use snapshot::SnapshotError;
 use waiting::{MaybeWaiting, Waiting};
 
+pub mod clock;
+pub mod describe;
 pub mod hash;
+pub mod lifecycle;
+pub mod observer;
 pub mod operators;
+pub mod snapshot;
 pub mod waiting;
 
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ListenOptions {
+    pub replay_current: bool,
+}
+
+pub trait Emit<T> {
+    /// # Errors
+    ///
+    /// Hands the value back when the engine no longer takes updates, or when the queue it would
+    /// wait in is full and rejects it.
+    fn send(&self, value: T) -> Result<(), SendError<T>>;
+}
+
+impl<T> Emit<T> for Sender<T> {
+    fn send(&self, value: T) -> Result<(), SendError<T>> {
+        Sender::send(self, value)
+    }
+}
+
 pub trait Engine {
-    fn start(&self) -> impl MaybeWaiting<()>;
+    type Sender<T: RType>: Emit<T> + Clone + Send;
+
+    fn lifecycle(&self) -> Lifecycle;
+    /// # Errors
+    ///
+    /// Fails unless the engine was just created.
+    fn start(&self) -> impl MaybeWaiting<Result<(), LifecycleError>>;
+    /// # Errors
+    ///
+    /// Fails unless the engine is running.
+    fn pause(&self) -> impl MaybeWaiting<Result<(), LifecycleError>>;
+    /// # Errors
+    ///
+    /// Fails unless the engine is paused.
+    fn resume(&self) -> impl MaybeWaiting<Result<(), LifecycleError>>;
     fn shutdown(self) -> impl Waiting<()>;
-    fn listen<T: RType>(&self, signal: Signal<T>) -> impl MaybeWaiting<Receiver<T>>;
-    fn emit<T: RType>(&self, input: InputRef) -> impl MaybeWaiting<Sender<T>>;
+    fn shutdown_graceful(self) -> impl Waiting<usize>;
+    fn listen<T: RType>(&self, signal: Signal<T>) -> impl MaybeWaiting<Receiver<T>> {
+        self.listen_with(signal, ListenOptions::default())
+    }
+    fn listen_with<T: RType>(
+        &self,
+        signal: Signal<T>,
+        options: ListenOptions,
+    ) -> impl MaybeWaiting<Receiver<T>>;
+    // Events have no current value, so there is nothing to replay.
+    fn listen_event<T: RType>(&self, event: Event<T>) -> impl MaybeWaiting<Receiver<T>> {
+        self.listen(event.into_signal())
+    }
+    fn emit<T: RType>(&self, input: InputRef) -> impl MaybeWaiting<Self::Sender<T>>;
+    fn describe(&self) -> impl Waiting<GraphDescription>;
+    /// # Errors
+    ///
+    /// Fails when the graph reads an unnamed input, or holds state that cannot be written, such
+    /// as custom values.
+    fn snapshot(&self) -> impl Waiting<Result<Vec<u8>, SnapshotError>>;
+    /// # Errors
+    ///
+    /// Fails when the bytes are not a snapshot of this format version.
+    fn restore(bytes: &[u8]) -> Result<Self, SnapshotError>
+    where
+        Self: Sized;
 }
@@ -0,0 +1,114 @@
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lifecycle {
+    Created,
+    Running,
+    Paused,
+    ShuttingDown,
+    Stopped,
+}
+
+impl Lifecycle {
+    const ALL: [Lifecycle; 5] = [
+        Lifecycle::Created,
+        Lifecycle::Running,
+        Lifecycle::Paused,
+        Lifecycle::ShuttingDown,
+        Lifecycle::Stopped,
+    ];
+
+    pub fn can_transition(self, to: Lifecycle) -> bool {
+        use Lifecycle::{Created, Paused, Running, ShuttingDown, Stopped};
+        match to {
+            Created => false,
+            Running => matches!(self, Created | Paused),
+            Paused => self == Running,
+            ShuttingDown => matches!(self, Created | Running | Paused),
+            Stopped => self == ShuttingDown,
+        }
+    }
+
+    /// # Errors
+    ///
+    /// Fails when the engine cannot go to `to` from here.
+    pub fn transition(self, to: Lifecycle) -> Result<Lifecycle, LifecycleError> {
+        if self.can_transition(to) {
+            Ok(to)
+        } else {
+            Err(LifecycleError { from: self, to })
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LifecycleError {
+    pub from: Lifecycle,
+    pub to: Lifecycle,
+}
+
+impl Display for LifecycleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "engine cannot go from {:?} to {:?}", self.from, self.to)
+    }
+}
+
+impl Error for LifecycleError {}
+
+// Lets the engine handle validate transitions synchronously, while the engine thread applies
+// them in command order.
+pub struct SharedLifecycle(AtomicU8);
+
+impl SharedLifecycle {
+    pub fn new() -> Self {
+        Self(AtomicU8::new(Lifecycle::Created as u8))
+    }
+
+    pub fn get(&self) -> Lifecycle {
+        Lifecycle::ALL[self.0.load(Ordering::Acquire) as usize]
+    }
+
+    /// # Errors
+    ///
+    /// Fails when the engine is no longer at `from`, or cannot go from there to `to`.
+    pub fn transition_from(&self, from: Lifecycle, to: Lifecycle) -> Result<(), LifecycleError> {
+        from.transition(to)?;
+        self.0
+            .compare_exchange(from as u8, to as u8, Ordering::AcqRel, Ordering::Acquire)
+            .map(|_| ())
+            .map_err(|actual| LifecycleError {
+                from: Lifecycle::ALL[actual as usize],
+                to,
+            })
+    }
+
+    /// # Errors
+    ///
+    /// Fails when the engine cannot go to `to` from where it is.
+    pub fn transition(&self, to: Lifecycle) -> Result<Lifecycle, LifecycleError> {
+        let mut current = self.get();
+        loop {
+            current.transition(to)?;
+            match self.0.compare_exchange(
+                current as u8,
+                to as u8,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => return Ok(current),
+                Err(actual) => current = Lifecycle::ALL[actual as usize],
+            }
+        }
+    }
+}
+
+impl Default for SharedLifecycle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,316 @@
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    lifecycle::{Lifecycle, LifecycleError},
+    operators::{
+        types::{RType, Type, Wrapper},
+        InputRef,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CommandKind {
+    Start,
+    Shutdown,
+    Listen,
+    Emit,
+    Pause,
+    Resume,
+    Describe,
+    Snapshot,
+    Detach,
+    Absorb,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QueueKind {
+    Prestart,
+    Pause,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueueOverflow {
+    pub queue: QueueKind,
+    pub capacity: usize,
+}
+
+impl Display for QueueOverflow {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?} queue is full ({} updates), update rejected",
+            self.queue, self.capacity
+        )
+    }
+}
+
+impl Error for QueueOverflow {}
+
+// A checked operator whose result did not fit its type, which the operator holds as its error
+// until its operands change again.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ArithmeticOverflow {
+    pub left: u64,
+    pub right: u64,
+}
+
+impl Display for ArithmeticOverflow {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "adding {} and {} overflowed", self.left, self.right)
+    }
+}
+
+impl Error for ArithmeticOverflow {}
+
+impl RType for ArithmeticOverflow {
+    fn into_type() -> Type {
+        Type::Overflow
+    }
+
+    fn coerce(wrapper: Wrapper) -> Self {
+        match wrapper {
+            Wrapper::Overflow(overflow) => overflow,
+            wrapper => unreachable!("{wrapper:?} is not an overflow"),
+        }
+    }
+
+    fn wrap(self) -> Wrapper {
+        Wrapper::Overflow(self)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Propagation {
+    pub input: usize,
+    // Whether the update ran through a compiled tape rather than the interpreter.
+    pub compiled: bool,
+    pub touched: usize,
+    pub latency: Duration,
+}
+
+pub trait EngineObserver: Send + Sync {
+    fn command_received(&self, _command: CommandKind) {}
+    fn lifecycle_changed(&self, _from: Lifecycle, _to: Lifecycle) {}
+    fn lifecycle_rejected(&self, _error: LifecycleError) {}
+    fn update_propagated(&self, _propagation: Propagation) {}
+    fn listener_removed(&self, _node: usize) {}
+    fn emitter_disconnected(&self, _input: InputRef) {}
+    fn update_queued(&self, _queue: QueueKind) {}
+    fn update_dropped(&self, _queue: QueueKind) {}
+    fn queue_overflowed(&self, _overflow: QueueOverflow) {}
+    fn arithmetic_overflowed(&self, _node: usize, _overflow: ArithmeticOverflow) {}
+    fn queue_depth(&self, _queue: QueueKind, _depth: usize) {}
+    fn live_listeners(&self, _count: usize) {}
+}
+
+pub struct NoopObserver;
+
+impl EngineObserver for NoopObserver {}
+
+const BUCKETS: usize = 64;
+
+// Bucket `i` counts samples of at most `2^i` nanoseconds.
+pub struct Histogram {
+    buckets: [AtomicU64; BUCKETS],
+}
+
+impl Histogram {
+    pub fn new() -> Self {
+        Self {
+            buckets: std::array::from_fn(|_| AtomicU64::new(0)),
+        }
+    }
+
+    pub fn record(&self, sample: Duration) {
+        let nanos = u64::try_from(sample.as_nanos()).unwrap_or(u64::MAX);
+        let bucket = (u64::BITS - nanos.saturating_sub(1).leading_zeros()) as usize;
+        self.buckets[bucket.min(BUCKETS - 1)].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.buckets
+            .iter()
+            .map(|bucket| bucket.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    pub fn percentile(&self, percentile: f64) -> Option<Duration> {
+        let counts = self
+            .buckets
+            .iter()
+            .map(|bucket| bucket.load(Ordering::Relaxed))
+            .collect::<Vec<_>>();
+        let total: u64 = counts.iter().sum();
+        if total == 0 {
+            return None;
+        }
+        #[allow(
+            clippy::cast_precision_loss,
+            clippy::cast_possible_truncation,
+            clippy::cast_sign_loss
+        )]
+        let rank = ((total as f64 * percentile.clamp(0.0, 1.0)).ceil() as u64).max(1);
+        let mut seen = 0;
+        counts.iter().enumerate().find_map(|(bucket, count)| {
+            seen += count;
+            (seen >= rank).then(|| Duration::from_nanos(1 << bucket))
+        })
+    }
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricsReport {
+    pub commands: u64,
+    pub lifecycle_rejections: u64,
+    pub updates: u64,
+    pub compiled_updates: u64,
+    // Averaged over the lifetime of the metrics.
+    pub mean_updates_per_second: f64,
+    pub latency_p50: Option<Duration>,
+    pub latency_p99: Option<Duration>,
+    pub queue_depth: usize,
+    pub max_queue_depth: usize,
+    pub updates_queued: u64,
+    pub updates_dropped: u64,
+    pub queue_overflows: u64,
+    pub arithmetic_overflows: u64,
+    pub live_listeners: usize,
+    pub listeners_removed: u64,
+    pub emitters_disconnected: u64,
+}
+
+pub struct Metrics {
+    created: Instant,
+    commands: AtomicU64,
+    lifecycle_rejections: AtomicU64,
+    updates: AtomicU64,
+    compiled_updates: AtomicU64,
+    latency: Histogram,
+    queue_depth: AtomicUsize,
+    max_queue_depth: AtomicUsize,
+    updates_queued: AtomicU64,
+    updates_dropped: AtomicU64,
+    queue_overflows: AtomicU64,
+    arithmetic_overflows: AtomicU64,
+    live_listeners: AtomicUsize,
+    listeners_removed: AtomicU64,
+    emitters_disconnected: AtomicU64,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self {
+            created: Instant::now(),
+            commands: AtomicU64::new(0),
+            lifecycle_rejections: AtomicU64::new(0),
+            updates: AtomicU64::new(0),
+            compiled_updates: AtomicU64::new(0),
+            latency: Histogram::new(),
+            queue_depth: AtomicUsize::new(0),
+            max_queue_depth: AtomicUsize::new(0),
+            updates_queued: AtomicU64::new(0),
+            updates_dropped: AtomicU64::new(0),
+            queue_overflows: AtomicU64::new(0),
+            arithmetic_overflows: AtomicU64::new(0),
+            live_listeners: AtomicUsize::new(0),
+            listeners_removed: AtomicU64::new(0),
+            emitters_disconnected: AtomicU64::new(0),
+        }
+    }
+
+    pub fn latency(&self) -> &Histogram {
+        &self.latency
+    }
+
+    pub fn report(&self) -> MetricsReport {
+        let updates = self.updates.load(Ordering::Relaxed);
+        #[allow(clippy::cast_precision_loss)]
+        let mean_updates_per_second = updates as f64 / self.created.elapsed().as_secs_f64();
+        MetricsReport {
+            commands: self.commands.load(Ordering::Relaxed),
+            lifecycle_rejections: self.lifecycle_rejections.load(Ordering::Relaxed),
+            updates,
+            compiled_updates: self.compiled_updates.load(Ordering::Relaxed),
+            mean_updates_per_second,
+            latency_p50: self.latency.percentile(0.5),
+            latency_p99: self.latency.percentile(0.99),
+            queue_depth: self.queue_depth.load(Ordering::Relaxed),
+            max_queue_depth: self.max_queue_depth.load(Ordering::Relaxed),
+            updates_queued: self.updates_queued.load(Ordering::Relaxed),
+            updates_dropped: self.updates_dropped.load(Ordering::Relaxed),
+            queue_overflows: self.queue_overflows.load(Ordering::Relaxed),
+            arithmetic_overflows: self.arithmetic_overflows.load(Ordering::Relaxed),
+            live_listeners: self.live_listeners.load(Ordering::Relaxed),
+            listeners_removed: self.listeners_removed.load(Ordering::Relaxed),
+            emitters_disconnected: self.emitters_disconnected.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EngineObserver for Metrics {
+    fn command_received(&self, _command: CommandKind) {
+        self.commands.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn lifecycle_rejected(&self, _error: LifecycleError) {
+        self.lifecycle_rejections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn update_propagated(&self, propagation: Propagation) {
+        self.updates.fetch_add(1, Ordering::Relaxed);
+        if propagation.compiled {
+            self.compiled_updates.fetch_add(1, Ordering::Relaxed);
+        }
+        self.latency.record(propagation.latency);
+    }
+
+    fn listener_removed(&self, _node: usize) {
+        self.listeners_removed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn emitter_disconnected(&self, _input: InputRef) {
+        self.emitters_disconnected.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn update_queued(&self, _queue: QueueKind) {
+        self.updates_queued.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn update_dropped(&self, _queue: QueueKind) {
+        self.updates_dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn queue_overflowed(&self, _overflow: QueueOverflow) {
+        self.queue_overflows.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn arithmetic_overflowed(&self, _node: usize, _overflow: ArithmeticOverflow) {
+        self.arithmetic_overflows.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn queue_depth(&self, _queue: QueueKind, depth: usize) {
+        self.queue_depth.store(depth, Ordering::Relaxed);
+        self.max_queue_depth.fetch_max(depth, Ordering::Relaxed);
+    }
+
+    fn live_listeners(&self, count: usize) {
+        self.live_listeners.store(count, Ordering::Relaxed);
+    }
+}
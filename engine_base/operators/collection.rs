@@ -0,0 +1,235 @@
+use std::{
+    fmt::{self, Debug, Formatter},
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use rustc_hash::FxHashMap;
+
+use super::types::{RType, Type, Wrapper};
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Delta {
+    // Inserts an entry, or replaces the value `old` held by the same key.
+    Insert {
+        key: Wrapper,
+        value: Wrapper,
+        old: Option<Wrapper>,
+    },
+    Remove {
+        key: Wrapper,
+        old: Option<Wrapper>,
+    },
+}
+
+// The value of a collection node: the changes of its latest update, along with the entries they
+// led to. Dependents only ever read the changes, so entries are shared by every value a node
+// holds and only written by the node itself.
+#[derive(Clone, Default)]
+pub struct Collection {
+    pub changes: Vec<Delta>,
+    entries: Arc<Mutex<FxHashMap<Wrapper, Wrapper>>>,
+}
+
+impl Collection {
+    pub fn from_entries(entries: impl IntoIterator<Item = (Wrapper, Wrapper)>) -> Self {
+        Self {
+            changes: Vec::new(),
+            entries: Arc::new(Mutex::new(entries.into_iter().collect())),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, FxHashMap<Wrapper, Wrapper>> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    pub fn get(&self, key: &Wrapper) -> Option<Wrapper> {
+        self.lock().get(key).cloned()
+    }
+
+    pub fn entries(&self) -> Vec<(Wrapper, Wrapper)> {
+        self.lock()
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    }
+
+    // Sets or removes the value of every key in turn, recording the values they replace.
+    // Changes that leave an entry as it was are dropped.
+    pub fn apply(&self, changes: impl IntoIterator<Item = (Wrapper, Option<Wrapper>)>) -> Self {
+        let mut entries = self.lock();
+        let mut applied = Vec::new();
+        for (key, value) in changes {
+            match value {
+                Some(value) => {
+                    let old = entries.insert(key.clone(), value.clone());
+                    if old.as_ref() != Some(&value) {
+                        applied.push(Delta::Insert { key, value, old });
+                    }
+                }
+                None => {
+                    if let Some(old) = entries.remove(&key) {
+                        applied.push(Delta::Remove {
+                            key,
+                            old: Some(old),
+                        });
+                    }
+                }
+            }
+        }
+        Self {
+            changes: applied,
+            entries: Arc::clone(&self.entries),
+        }
+    }
+
+    // The same entries, with every one of them as an insert, for listeners that join late.
+    pub fn replayed(&self) -> Self {
+        let changes = self
+            .lock()
+            .iter()
+            .map(|(key, value)| Delta::Insert {
+                key: key.clone(),
+                value: value.clone(),
+                old: None,
+            })
+            .collect();
+        Self {
+            changes,
+            entries: Arc::clone(&self.entries),
+        }
+    }
+
+    // Changes as an emitter sent them, before they are applied to any entries.
+    pub fn requested(&self) -> impl Iterator<Item = (Wrapper, Option<Wrapper>)> + '_ {
+        self.changes.iter().map(|change| match change {
+            Delta::Insert { key, value, .. } => (key.clone(), Some(value.clone())),
+            Delta::Remove { key, .. } => (key.clone(), None),
+        })
+    }
+}
+
+impl PartialEq for Collection {
+    fn eq(&self, other: &Self) -> bool {
+        self.changes == other.changes && Arc::ptr_eq(&self.entries, &other.entries)
+    }
+}
+
+impl Eq for Collection {}
+
+impl Hash for Collection {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.changes.hash(state);
+        Arc::as_ptr(&self.entries).hash(state);
+    }
+}
+
+impl Debug for Collection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Collection")
+            .field("changes", &self.changes)
+            .field("entries", &self.len())
+            .finish()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Change<K, V> {
+    // Inserts an entry, or replaces the value of an existing one.
+    Insert(K, V),
+    Remove(K),
+}
+
+// A map signal propagates changes rather than contents: every value is a batch of changes, and
+// the engine keeps the entries, so listeners that replay receive every entry as an insert.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Map<K, V> {
+    changes: Vec<Change<K, V>>,
+    marker: PhantomData<fn() -> (K, V)>,
+}
+
+impl<K, V> Map<K, V> {
+    pub fn new() -> Self {
+        Self {
+            changes: Vec::new(),
+            marker: PhantomData,
+        }
+    }
+
+    pub fn insert(mut self, key: K, value: V) -> Self {
+        self.changes.push(Change::Insert(key, value));
+        self
+    }
+
+    pub fn remove(mut self, key: K) -> Self {
+        self.changes.push(Change::Remove(key));
+        self
+    }
+
+    pub fn changes(&self) -> &[Change<K, V>] {
+        &self.changes
+    }
+
+    pub fn into_changes(self) -> Vec<Change<K, V>> {
+        self.changes
+    }
+}
+
+impl<K, V> Default for Map<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: RType, V: RType> RType for Map<K, V> {
+    fn into_type() -> Type {
+        Type::map(K::into_type(), V::into_type())
+    }
+
+    fn coerce(wrapper: Wrapper) -> Self {
+        match wrapper {
+            Wrapper::Map(collection) => Self {
+                changes: collection
+                    .requested()
+                    .map(|(key, value)| match value {
+                        Some(value) => Change::Insert(K::coerce(key), V::coerce(value)),
+                        None => Change::Remove(K::coerce(key)),
+                    })
+                    .collect(),
+                marker: PhantomData,
+            },
+            wrapper => unreachable!("{wrapper:?} is not a map"),
+        }
+    }
+
+    fn wrap(self) -> Wrapper {
+        let changes = self
+            .changes
+            .into_iter()
+            .map(|change| match change {
+                Change::Insert(key, value) => Delta::Insert {
+                    key: key.wrap(),
+                    value: value.wrap(),
+                    old: None,
+                },
+                Change::Remove(key) => Delta::Remove {
+                    key: key.wrap(),
+                    old: None,
+                },
+            })
+            .collect();
+        Wrapper::Map(Arc::new(Collection {
+            changes,
+            entries: Arc::default(),
+        }))
+    }
+}
@@ -1,5 +1,7 @@
-use crate::hash::Prehashed;
+use crate::{hash::Prehashed, observer::ArithmeticOverflow};
 use std::{
+    fmt::{self, Debug, Display, Formatter},
+    hash::{Hash, Hasher},
     marker::PhantomData,
     ops::Add,
     sync::{
@@ -8,11 +10,18 @@ use std::{
     },
 };
 
-use types::{RType, Type};
+use collection::Map;
+use registry::DuplicateInputName;
+use rustc_hash::FxHashSet;
+use types::{RType, Type, Wrapper};
+use window::{Aggregate, Window};
 
+pub mod collection;
+pub mod registry;
 pub mod types;
+pub mod window;
 
-type Apt = Arc<Prehashed<Typed>>;
+pub(crate) type Apt = Arc<Prehashed<Typed>>;
 
 #[derive(Clone, Hash, PartialEq, Eq, Debug)]
 pub struct Typed {
@@ -20,27 +29,194 @@ pub struct Typed {
     pub rtype: Type,
 }
 
-#[derive(Hash, PartialEq, Eq, Copy, Clone, Debug)]
+#[derive(Hash, PartialEq, Eq, Copy, Clone)]
 pub struct InputRef {
     id: u64,
 }
 
+static ID_GENERATOR: AtomicU64 = AtomicU64::new(0);
+
 impl InputRef {
     fn new() -> Self {
-        static ID_GENERATOR: AtomicU64 = AtomicU64::new(0);
         Self {
             id: ID_GENERATOR.fetch_add(1, Ordering::AcqRel),
         }
     }
+
+    pub(crate) fn raw(self) -> u64 {
+        self.id
+    }
+
+    pub fn named(name: &str) -> Option<Self> {
+        registry::lookup(name)
+    }
+
+    pub fn name(self) -> Option<Arc<str>> {
+        registry::name_of(self)
+    }
+}
+
+impl Debug for InputRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "InputRef({name:?})"),
+            None => write!(f, "InputRef(#{})", self.id),
+        }
+    }
+}
+
+// A closure over wrapped values. Closures cannot be compared, so functions compare and hash by
+// identity, like custom values.
+pub struct Function<F: ?Sized = dyn Fn(Wrapper) -> Wrapper + Send + Sync>(Arc<F>);
+
+pub type Predicate = Function<dyn Fn(&Wrapper) -> bool + Send + Sync>;
+
+impl Function {
+    fn new<A: RType, B: RType>(f: impl Fn(A) -> B + Send + Sync + 'static) -> Self {
+        Self(Arc::new(move |value| f(A::coerce(value)).wrap()))
+    }
+
+    // For closures that only look at their argument, like keys to group by.
+    fn by_ref<A: RType, B: RType>(f: impl Fn(&A) -> B + Send + Sync + 'static) -> Self {
+        Self(Arc::new(move |value| f(&A::coerce(value)).wrap()))
+    }
+
+    pub fn call(&self, value: Wrapper) -> Wrapper {
+        (self.0)(value)
+    }
+}
+
+impl Predicate {
+    fn of<A: RType>(f: impl Fn(&A) -> bool + Send + Sync + 'static) -> Self {
+        Self(Arc::new(move |value: &Wrapper| {
+            f(&A::coerce(value.clone()))
+        }))
+    }
+
+    pub fn test(&self, value: &Wrapper) -> bool {
+        (self.0)(value)
+    }
+}
+
+impl<F: ?Sized> Clone for Function<F> {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+impl<F: ?Sized> PartialEq for Function<F> {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl<F: ?Sized> Eq for Function<F> {}
+
+impl<F: ?Sized> Hash for Function<F> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Arc::as_ptr(&self.0).cast::<()>().hash(state);
+    }
+}
+
+impl<F: ?Sized> Debug for Function<F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Function({:p})", Arc::as_ptr(&self.0).cast::<()>())
+    }
 }
 
 #[derive(Hash, PartialEq, Eq, Clone, Debug)]
 pub enum Desc {
     Input(InputRef),
     Add(Apt, Apt),
+    SaturatingAdd(Apt, Apt),
+    CheckedAdd(Apt, Apt),
+    Optional(Apt),
+    UnwrapOr(Apt, Wrapper),
+    MapErr(Apt, Function),
+    Recover(Apt, Function),
+    Errors(Apt),
+    OkOr(Apt, Wrapper),
+    Switch(Apt),
+    Filter(Apt, Predicate),
+    Map(Apt, Function),
+    GroupBy(Apt, Function),
+    Count(Apt),
+    Sum(Apt),
+    Window(Apt, Aggregate, Window),
+    LastN(Apt, usize),
+    Hold(Apt, Wrapper),
+    Changes(Apt),
+    // The event, and the signal whose value it takes.
+    Sample(Apt, Apt),
 }
 
 impl Desc {
+    pub fn operands(&self) -> Vec<&Apt> {
+        match self {
+            Desc::Input(_) => Vec::new(),
+            Desc::Add(left, right)
+            | Desc::SaturatingAdd(left, right)
+            | Desc::CheckedAdd(left, right) => vec![left, right],
+            Desc::Sample(event, signal) => vec![event, signal],
+            Desc::Optional(inner)
+            | Desc::UnwrapOr(inner, _)
+            | Desc::MapErr(inner, _)
+            | Desc::Recover(inner, _)
+            | Desc::Errors(inner)
+            | Desc::OkOr(inner, _)
+            | Desc::Switch(inner)
+            | Desc::Filter(inner, _)
+            | Desc::Map(inner, _)
+            | Desc::GroupBy(inner, _)
+            | Desc::Count(inner)
+            | Desc::Sum(inner)
+            | Desc::Window(inner, ..)
+            | Desc::LastN(inner, _)
+            | Desc::Hold(inner, _)
+            | Desc::Changes(inner) => vec![inner],
+        }
+    }
+
+    // Every input the signal reads, once each. Shared subexpressions are only walked once, so
+    // deep graphs with a lot of sharing stay cheap.
+    pub fn inputs(&self) -> Vec<InputRef> {
+        let mut res = Vec::new();
+        self.walk(
+            &mut |desc| {
+                if let Desc::Input(input) = desc {
+                    if !res.contains(input) {
+                        res.push(*input);
+                    }
+                }
+            },
+            &mut FxHashSet::default(),
+        );
+        res
+    }
+
+    // Whether the signal contains a switch, so its dependencies can change at runtime.
+    pub fn is_dynamic(&self) -> bool {
+        let mut res = false;
+        self.walk(
+            &mut |desc| res |= matches!(desc, Desc::Switch(_)),
+            &mut FxHashSet::default(),
+        );
+        res
+    }
+
+    fn walk(
+        &self,
+        visit: &mut impl FnMut(&Desc),
+        visited: &mut FxHashSet<*const Prehashed<Typed>>,
+    ) {
+        visit(self);
+        for operand in self.operands() {
+            if visited.insert(Arc::as_ptr(operand)) {
+                operand.desc.walk(visit, visited);
+            }
+        }
+    }
+
     fn with_type<T: RType>(self) -> Typed {
         Typed {
             desc: self,
@@ -49,6 +225,51 @@ impl Desc {
     }
 }
 
+impl Display for Desc {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Desc::Input(input) => match input.name() {
+                Some(name) => write!(f, "input({name:?})"),
+                None => write!(f, "input(#{})", input.id),
+            },
+            Desc::Add(left, right) => write!(f, "add({}, {})", left.desc, right.desc),
+            Desc::SaturatingAdd(left, right) => {
+                write!(f, "saturating_add({}, {})", left.desc, right.desc)
+            }
+            Desc::CheckedAdd(left, right) => {
+                write!(f, "checked_add({}, {})", left.desc, right.desc)
+            }
+            Desc::Optional(inner) => write!(f, "optional({})", inner.desc),
+            Desc::UnwrapOr(inner, default) => write!(f, "unwrap_or({}, {default:?})", inner.desc),
+            Desc::MapErr(inner, _) => write!(f, "map_err({}, ..)", inner.desc),
+            Desc::Recover(inner, _) => write!(f, "recover({}, ..)", inner.desc),
+            Desc::Errors(inner) => write!(f, "errors({})", inner.desc),
+            Desc::OkOr(inner, error) => write!(f, "ok_or({}, {error:?})", inner.desc),
+            Desc::Switch(inner) => write!(f, "switch({})", inner.desc),
+            Desc::Filter(inner, _) => write!(f, "filter({}, ..)", inner.desc),
+            Desc::Map(inner, _) => write!(f, "map({}, ..)", inner.desc),
+            Desc::GroupBy(inner, _) => write!(f, "group_by({}, ..)", inner.desc),
+            Desc::Count(inner) => write!(f, "count({})", inner.desc),
+            Desc::Sum(inner) => write!(f, "sum({})", inner.desc),
+            Desc::Window(inner, aggregate, window) => {
+                write!(f, "window_{aggregate}({}, {window})", inner.desc)
+            }
+            Desc::LastN(inner, n) => write!(f, "last_n({}, {n})", inner.desc),
+            Desc::Hold(inner, init) => write!(f, "hold({}, {init:?})", inner.desc),
+            Desc::Changes(inner) => write!(f, "changes({})", inner.desc),
+            Desc::Sample(event, signal) => {
+                write!(f, "snapshot({}, {})", event.desc, signal.desc)
+            }
+        }
+    }
+}
+
+impl Display for Typed {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {:?}", self.desc, self.rtype)
+    }
+}
+
 #[derive(Clone)]
 pub struct Signal<T>(Apt, PhantomData<T>);
 
@@ -57,10 +278,81 @@ impl<T> Signal<T> {
         Arc::clone(&self.0)
     }
 
+    // The signal of a named input, which is how graphs restored in a new process are reached.
+    pub fn named(name: &str) -> Option<Self>
+    where
+        T: RType,
+    {
+        let input_ref = registry::lookup(name)?;
+        Some(Desc::Input(input_ref).with_type::<T>().into())
+    }
+
     pub fn get_type(self) -> Type {
         self.0.rtype
     }
 }
+
+impl<T> Debug for Signal<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Signal({})", **self.0)
+    }
+}
+
+// Signals can be values themselves, which `switch` follows.
+impl<T: RType> RType for Signal<T> {
+    fn into_type() -> Type {
+        Type::signal(T::into_type())
+    }
+
+    fn coerce(wrapper: Wrapper) -> Self {
+        match wrapper {
+            Wrapper::Signal(signal) => Self(signal, PhantomData),
+            wrapper => unreachable!("{wrapper:?} is not a signal"),
+        }
+    }
+
+    fn wrap(self) -> Wrapper {
+        Wrapper::Signal(self.0)
+    }
+}
+
+// A discrete stream of occurrences. Unlike a signal, an event has no current value: listeners
+// only hear about occurrences after they start listening, and operators never read an event
+// between its occurrences.
+#[derive(Clone)]
+pub struct Event<T>(Apt, PhantomData<T>);
+
+impl<T> Event<T> {
+    pub fn get_desc(&self) -> Apt {
+        Arc::clone(&self.0)
+    }
+
+    pub fn named(name: &str) -> Option<Self>
+    where
+        T: RType,
+    {
+        let input_ref = registry::lookup(name)?;
+        Some(Desc::Input(input_ref).with_type::<T>().into())
+    }
+
+    // Engines listen to events like signals, without ever replaying them.
+    pub(crate) fn into_signal(self) -> Signal<T> {
+        Signal(self.0, PhantomData)
+    }
+}
+
+impl<T> Debug for Event<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Event({})", **self.0)
+    }
+}
+
+impl<T> From<Typed> for Event<T> {
+    fn from(desc: Typed) -> Self {
+        Self(Arc::new(desc.into()), PhantomData::<T>)
+    }
+}
+
 impl<T> From<Typed> for Signal<T> {
     fn from(desc: Typed) -> Self {
         Self(Arc::new(desc.into()), PhantomData::<T>)
@@ -73,6 +365,32 @@ pub fn input<T: RType>() -> (InputRef, Signal<T>) {
     (input_ref, sig)
 }
 
+// An input whose every emission is an occurrence.
+pub fn event<T: RType>() -> (InputRef, Event<T>) {
+    let input_ref = InputRef::new();
+    let event = Desc::Input(input_ref).with_type::<T>().into();
+    (input_ref, event)
+}
+
+/// # Errors
+///
+/// Fails when another input already claimed the name.
+pub fn input_named<T: RType>(name: &str) -> Result<(InputRef, Signal<T>), DuplicateInputName> {
+    let input_ref = registry::claim(name)?;
+    let sig = Desc::Input(input_ref).with_type::<T>().into();
+    Ok((input_ref, sig))
+}
+
+/// # Errors
+///
+/// Fails when another input already claimed the name.
+pub fn event_named<T: RType>(name: &str) -> Result<(InputRef, Event<T>), DuplicateInputName> {
+    let input_ref = registry::claim(name)?;
+    let event = Desc::Input(input_ref).with_type::<T>().into();
+    Ok((input_ref, event))
+}
+
+// Wraps around on overflow.
 pub fn add<T, Rhs>(left: Signal<T>, right: Signal<Rhs>) -> Signal<<T as Add<Rhs>>::Output>
 where
     T: RType,
@@ -84,3 +402,170 @@ where
         .with_type::<T::Output>()
         .into()
 }
+
+pub fn saturating_add<T, Rhs>(
+    left: Signal<T>,
+    right: Signal<Rhs>,
+) -> Signal<<T as Add<Rhs>>::Output>
+where
+    T: RType,
+    Rhs: RType,
+    T: Add<Rhs>,
+    <T as Add<Rhs>>::Output: RType,
+{
+    Desc::SaturatingAdd(left.get_desc(), right.get_desc())
+        .with_type::<T::Output>()
+        .into()
+}
+
+// Overflowing sums are errors, which engines also report to their observer.
+pub fn checked_add<T, Rhs>(
+    left: Signal<T>,
+    right: Signal<Rhs>,
+) -> Signal<Result<<T as Add<Rhs>>::Output, ArithmeticOverflow>>
+where
+    T: RType,
+    Rhs: RType,
+    T: Add<Rhs>,
+    <T as Add<Rhs>>::Output: RType,
+{
+    Desc::CheckedAdd(left.get_desc(), right.get_desc())
+        .with_type::<Result<T::Output, ArithmeticOverflow>>()
+        .into()
+}
+
+// Views a signal as optional, so listeners see missing values as `None` instead of skipping them.
+pub fn optional<T: RType>(signal: Signal<T>) -> Signal<Option<T>> {
+    Desc::Optional(signal.get_desc())
+        .with_type::<Option<T>>()
+        .into()
+}
+
+pub fn unwrap_or<T: RType>(signal: Signal<Option<T>>, default: T) -> Signal<T> {
+    Desc::UnwrapOr(signal.get_desc(), default.wrap())
+        .with_type::<T>()
+        .into()
+}
+
+// Signals fail by holding an `Err`, which flows through the graph like any other value.
+pub fn ok_or<T: RType, E: RType>(signal: Signal<Option<T>>, error: E) -> Signal<Result<T, E>> {
+    Desc::OkOr(signal.get_desc(), error.wrap())
+        .with_type::<Result<T, E>>()
+        .into()
+}
+
+// Every call creates a new node, since closures are compared by identity.
+pub fn map_err<T: RType, E: RType, F: RType>(
+    signal: Signal<Result<T, E>>,
+    f: impl Fn(E) -> F + Send + Sync + 'static,
+) -> Signal<Result<T, F>> {
+    Desc::MapErr(signal.get_desc(), Function::new(f))
+        .with_type::<Result<T, F>>()
+        .into()
+}
+
+pub fn recover<T: RType, E: RType>(
+    signal: Signal<Result<T, E>>,
+    f: impl Fn(E) -> T + Send + Sync + 'static,
+) -> Signal<T> {
+    Desc::Recover(signal.get_desc(), Function::new(f))
+        .with_type::<T>()
+        .into()
+}
+
+// Holds the latest error, and has no value while the signal succeeds, so listeners only hear
+// about failures.
+pub fn errors<T: RType, E: RType>(signal: Signal<Result<T, E>>) -> Signal<E> {
+    Desc::Errors(signal.get_desc()).with_type::<E>().into()
+}
+
+// Follows whichever signal `signal` currently holds, and has no value while it holds none.
+pub fn switch<T: RType>(signal: Signal<Signal<T>>) -> Signal<T> {
+    Desc::Switch(signal.get_desc()).with_type::<T>().into()
+}
+
+// Collection operators only look at the changes of each update, so their cost depends on how
+// much changed rather than on the size of the collection.
+pub fn filter<K: RType, V: RType>(
+    signal: Signal<Map<K, V>>,
+    f: impl Fn(&V) -> bool + Send + Sync + 'static,
+) -> Signal<Map<K, V>> {
+    Desc::Filter(signal.get_desc(), Predicate::of(f))
+        .with_type::<Map<K, V>>()
+        .into()
+}
+
+pub fn map<K: RType, V: RType, W: RType>(
+    signal: Signal<Map<K, V>>,
+    f: impl Fn(V) -> W + Send + Sync + 'static,
+) -> Signal<Map<K, W>> {
+    Desc::Map(signal.get_desc(), Function::new(f))
+        .with_type::<Map<K, W>>()
+        .into()
+}
+
+// Counts the entries of every group, and drops groups once they are empty.
+pub fn group_by<K: RType, V: RType, G: RType>(
+    signal: Signal<Map<K, V>>,
+    f: impl Fn(&V) -> G + Send + Sync + 'static,
+) -> Signal<Map<G, u64>> {
+    Desc::GroupBy(signal.get_desc(), Function::by_ref(f))
+        .with_type::<Map<G, u64>>()
+        .into()
+}
+
+pub fn count<K: RType, V: RType>(signal: Signal<Map<K, V>>) -> Signal<u64> {
+    Desc::Count(signal.get_desc()).with_type::<u64>().into()
+}
+
+// Wraps around on overflow, like `add`.
+pub fn sum<K: RType>(signal: Signal<Map<K, u64>>) -> Signal<u64> {
+    Desc::Sum(signal.get_desc()).with_type::<u64>().into()
+}
+
+// Aggregates the updates of a signal within a window of time, as read from the engine's clock.
+pub fn window_sum(signal: Signal<u64>, window: impl Into<Window>) -> Signal<u64> {
+    Desc::Window(signal.get_desc(), Aggregate::Sum, window.into())
+        .with_type::<u64>()
+        .into()
+}
+
+pub fn window_avg(signal: Signal<u64>, window: impl Into<Window>) -> Signal<u64> {
+    Desc::Window(signal.get_desc(), Aggregate::Avg, window.into())
+        .with_type::<u64>()
+        .into()
+}
+
+pub fn window_count<T: RType>(signal: Signal<T>, window: impl Into<Window>) -> Signal<u64> {
+    Desc::Window(signal.get_desc(), Aggregate::Count, window.into())
+        .with_type::<u64>()
+        .into()
+}
+
+// The latest `n` updates of a signal, keyed by how many updates came before each of them.
+pub fn last_n<T: RType>(signal: Signal<T>, n: usize) -> Signal<Map<u64, T>> {
+    Desc::LastN(signal.get_desc(), n)
+        .with_type::<Map<u64, T>>()
+        .into()
+}
+
+// Holds the latest occurrence of an event, and `init` until the first one.
+pub fn hold<T: RType>(event: Event<T>, init: T) -> Signal<T> {
+    Desc::Hold(event.get_desc(), init.wrap())
+        .with_type::<T>()
+        .into()
+}
+
+// Occurs whenever the signal's value changes. Updates that leave the value as it was, which
+// signals still notify their listeners about, are not occurrences.
+pub fn changes<T: RType>(signal: Signal<T>) -> Event<T> {
+    Desc::Changes(signal.get_desc()).with_type::<T>().into()
+}
+
+// Occurs with the current value of `signal` whenever `event` occurs. Updates of the signal alone
+// are not occurrences.
+pub fn snapshot<T: RType, U: RType>(event: Event<T>, signal: Signal<U>) -> Event<U> {
+    Desc::Sample(event.get_desc(), signal.get_desc())
+        .with_type::<U>()
+        .into()
+}
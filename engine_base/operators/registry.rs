@@ -0,0 +1,66 @@
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    sync::{Arc, LazyLock, Mutex, MutexGuard, PoisonError},
+};
+
+use rustc_hash::FxHashMap;
+
+use super::InputRef;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct DuplicateInputName(pub Arc<str>);
+
+impl Display for DuplicateInputName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "input named {:?} already exists", self.0)
+    }
+}
+
+impl Error for DuplicateInputName {}
+
+#[derive(Default)]
+struct Registry {
+    by_name: FxHashMap<Arc<str>, InputRef>,
+    names: FxHashMap<InputRef, Arc<str>>,
+}
+
+impl Registry {
+    fn insert(&mut self, name: &str) -> InputRef {
+        let input = InputRef::new();
+        let name: Arc<str> = name.into();
+        self.by_name.insert(Arc::clone(&name), input);
+        self.names.insert(input, name);
+        input
+    }
+}
+
+static REGISTRY: LazyLock<Mutex<Registry>> = LazyLock::new(Mutex::default);
+
+fn registry() -> MutexGuard<'static, Registry> {
+    REGISTRY.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+pub(crate) fn claim(name: &str) -> Result<InputRef, DuplicateInputName> {
+    let mut registry = registry();
+    if let Some((name, _)) = registry.by_name.get_key_value(name) {
+        return Err(DuplicateInputName(Arc::clone(name)));
+    }
+    Ok(registry.insert(name))
+}
+
+pub(crate) fn lookup_or_claim(name: &str) -> InputRef {
+    let mut registry = registry();
+    match registry.by_name.get(name) {
+        Some(input) => *input,
+        None => registry.insert(name),
+    }
+}
+
+pub(crate) fn lookup(name: &str) -> Option<InputRef> {
+    registry().by_name.get(name).copied()
+}
+
+pub(crate) fn name_of(input: InputRef) -> Option<Arc<str>> {
+    registry().names.get(&input).cloned()
+}
@@ -1,12 +1,127 @@
+use std::{
+    any::{type_name, Any, TypeId},
+    fmt::{self, Debug, Formatter},
+    hash::{Hash, Hasher},
+    sync::{Arc, LazyLock, Mutex, PoisonError},
+};
+
+use rustc_hash::FxHashMap;
+
+use super::{collection::Collection, Apt};
+use crate::observer::ArithmeticOverflow;
+
 #[derive(Debug, Hash, PartialEq, Eq, Copy, Clone)]
 pub enum Type {
     U64,
+    Str,
+    Overflow,
+    Custom(CustomType),
+    Option(&'static Type),
+    Result(&'static Type, &'static Type),
+    Signal(&'static Type),
+    Map(&'static Type, &'static Type),
+}
+
+static INNER_TYPES: LazyLock<Mutex<FxHashMap<Type, &'static Type>>> = LazyLock::new(Mutex::default);
+
+// Inner types are interned, so `Type` stays `Copy`.
+fn intern(inner: Type) -> &'static Type {
+    let mut types = INNER_TYPES.lock().unwrap_or_else(PoisonError::into_inner);
+    types
+        .entry(inner)
+        .or_insert_with(|| Box::leak(Box::new(inner)))
+}
+
+impl Type {
+    pub fn option(inner: Type) -> Self {
+        Type::Option(intern(inner))
+    }
+
+    pub fn result(ok: Type, err: Type) -> Self {
+        Type::Result(intern(ok), intern(err))
+    }
+
+    pub fn signal(inner: Type) -> Self {
+        Type::Signal(intern(inner))
+    }
+
+    pub fn map(key: Type, value: Type) -> Self {
+        Type::Map(intern(key), intern(value))
+    }
+
+    pub fn custom<T: CustomValue + Clone + Default>(name: &'static str) -> Self {
+        Type::Custom(CustomType::of::<T>(name))
+    }
+
+    // Whether values of the type can hold custom values, which snapshots cannot write.
+    pub fn holds_custom(self) -> bool {
+        match self {
+            Type::U64 | Type::Str | Type::Overflow => false,
+            Type::Custom(_) => true,
+            Type::Option(inner) | Type::Signal(inner) => inner.holds_custom(),
+            Type::Result(left, right) | Type::Map(left, right) => {
+                left.holds_custom() || right.holds_custom()
+            }
+        }
+    }
+}
+
+// A type defined outside this crate, usually through `#[derive(RType)]`. Types are identified
+// by their `TypeId`, while their name, which the derive keeps stable across builds, is only used
+// to describe them.
+#[derive(Copy, Clone)]
+pub struct CustomType {
+    id: TypeId,
+    name: &'static str,
+    zeroed: fn() -> Wrapper,
+}
+
+impl CustomType {
+    pub fn of<T: CustomValue + Clone + Default>(name: &'static str) -> Self {
+        Self {
+            id: TypeId::of::<T>(),
+            name,
+            zeroed: || Wrapper::custom(T::default()),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        self.name
+    }
+}
+
+impl PartialEq for CustomType {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for CustomType {}
+
+impl Hash for CustomType {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl Debug for CustomType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name)
+    }
 }
 
 pub trait RType: Send + Sync + 'static {
     fn into_type() -> Type;
     fn coerce(wrapper: Wrapper) -> Self;
     fn wrap(self) -> Wrapper;
+
+    // What listeners receive for a missing value, if they can represent one at all.
+    fn missing() -> Option<Self>
+    where
+        Self: Sized,
+    {
+        None
+    }
 }
 
 impl RType for u64 {
@@ -17,6 +132,7 @@ impl RType for u64 {
     fn coerce(wrapper: Wrapper) -> Self {
         match wrapper {
             Wrapper::U64(value) => value,
+            wrapper => unreachable!("{wrapper:?} is not a u64"),
         }
     }
 
@@ -25,21 +141,257 @@ impl RType for u64 {
     }
 }
 
-#[derive(Debug, Hash, PartialEq, Eq, Clone)]
+// Cloning a wrapper never copies the value itself: large values are shared behind an `Arc`, so
+// fields, replays and listeners all point at the same allocation.
+impl RType for Arc<str> {
+    fn into_type() -> Type {
+        Type::Str
+    }
+
+    fn coerce(wrapper: Wrapper) -> Self {
+        match wrapper {
+            Wrapper::Str(value) => value,
+            wrapper => unreachable!("{wrapper:?} is not a string"),
+        }
+    }
+
+    fn wrap(self) -> Wrapper {
+        Wrapper::Str(self)
+    }
+}
+
+// `Some` values share the representation of the inner type, so `Option<Option<T>>` cannot tell
+// `Some(None)` from `None`.
+impl<T: RType> RType for Option<T> {
+    fn into_type() -> Type {
+        Type::option(T::into_type())
+    }
+
+    fn coerce(wrapper: Wrapper) -> Self {
+        match wrapper {
+            Wrapper::Missing => None,
+            wrapper => Some(T::coerce(wrapper)),
+        }
+    }
+
+    fn wrap(self) -> Wrapper {
+        self.map_or(Wrapper::Missing, RType::wrap)
+    }
+
+    fn missing() -> Option<Self> {
+        Some(None)
+    }
+}
+
+// What arithmetic does when a result does not fit its type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Overflow {
+    Wrapping,
+    Saturating,
+    Checked,
+}
+
+impl Overflow {
+    pub fn add_u64(self, lhs: u64, rhs: u64) -> Option<u64> {
+        match self {
+            Overflow::Wrapping => Some(lhs.wrapping_add(rhs)),
+            Overflow::Saturating => Some(lhs.saturating_add(rhs)),
+            Overflow::Checked => lhs.checked_add(rhs),
+        }
+    }
+}
+
+// `Ok` values share the representation of the value type, like `Some` values do.
+impl<T: RType, E: RType> RType for Result<T, E> {
+    fn into_type() -> Type {
+        Type::result(T::into_type(), E::into_type())
+    }
+
+    fn coerce(wrapper: Wrapper) -> Self {
+        match wrapper {
+            Wrapper::Err(error) => Err(E::coerce(Arc::unwrap_or_clone(error))),
+            wrapper => Ok(T::coerce(wrapper)),
+        }
+    }
+
+    fn wrap(self) -> Wrapper {
+        match self {
+            Ok(value) => value.wrap(),
+            Err(error) => Wrapper::Err(Arc::new(error.wrap())),
+        }
+    }
+
+    fn missing() -> Option<Self> {
+        T::missing().map(Ok)
+    }
+}
+
+// What a custom value needs to flow through signals: changes are found by comparing values, and
+// values can be the keys of maps.
+pub trait CustomValue: Any + Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+    fn into_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync>;
+    fn dyn_eq(&self, other: &dyn CustomValue) -> bool;
+    fn dyn_hash(&self, state: &mut dyn Hasher);
+}
+
+impl<T: Any + Eq + Hash + Send + Sync> CustomValue for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync> {
+        self
+    }
+
+    fn dyn_eq(&self, other: &dyn CustomValue) -> bool {
+        other.as_any().downcast_ref::<T>() == Some(self)
+    }
+
+    fn dyn_hash(&self, mut state: &mut dyn Hasher) {
+        self.hash(&mut state);
+    }
+}
+
+#[derive(Clone)]
 pub enum Wrapper {
     U64(u64),
+    Str(Arc<str>),
+    Overflow(ArithmeticOverflow),
+    Custom(Arc<dyn CustomValue>),
+    // No value yet, or an explicit `None`.
+    Missing,
+    Err(Arc<Wrapper>),
+    Signal(Apt),
+    Map(Arc<Collection>),
 }
 
 impl Wrapper {
     pub fn zeroed(rtype: Type) -> Self {
         match rtype {
             Type::U64 => Wrapper::U64(0),
+            Type::Str => Wrapper::Str(Arc::from("")),
+            Type::Overflow => Wrapper::Overflow(ArithmeticOverflow::default()),
+            Type::Custom(custom) => (custom.zeroed)(),
+            Type::Option(_) | Type::Signal(_) => Wrapper::Missing,
+            Type::Result(ok, _) => Wrapper::zeroed(*ok),
+            Type::Map(..) => Wrapper::Map(Arc::default()),
+        }
+    }
+
+    // The value a node holds after `update` was emitted to it. Maps merge the changes into their
+    // entries, starting from none, while any other value replaces the current one.
+    pub fn apply(&self, update: Wrapper) -> Self {
+        match (self, update) {
+            (Wrapper::Map(current), Wrapper::Map(update)) => {
+                Wrapper::Map(Arc::new(current.apply(update.requested())))
+            }
+            (_, Wrapper::Map(update)) => {
+                Wrapper::Map(Arc::new(Collection::default().apply(update.requested())))
+            }
+            (_, update) => update,
+        }
+    }
+
+    // What listeners that join late receive: maps replay every entry as an insert.
+    pub fn replayed(&self) -> Self {
+        match self {
+            Wrapper::Map(collection) => Wrapper::Map(Arc::new(collection.replayed())),
+            value => value.clone(),
+        }
+    }
+
+    // Merges two updates of the same input into one, which for maps keeps the changes of both.
+    pub fn coalesce(self, later: Wrapper) -> Self {
+        match (self, later) {
+            (Wrapper::Map(earlier), Wrapper::Map(later)) => {
+                let mut res = Arc::unwrap_or_clone(earlier);
+                res.changes.extend(later.changes.iter().cloned());
+                Wrapper::Map(Arc::new(res))
+            }
+            (_, later) => later,
         }
     }
 
-    pub fn add(&self, rhs: &Wrapper) -> Self {
+    pub fn custom<T: CustomValue>(value: T) -> Self {
+        Wrapper::Custom(Arc::new(value))
+    }
+
+    // Takes the value out of a custom wrapper, cloning it only when it is still shared.
+    pub fn downcast<T: CustomValue + Clone>(self) -> T {
+        match self {
+            Wrapper::Custom(value) => match value.into_any().downcast::<T>() {
+                Ok(value) => Arc::unwrap_or_clone(value),
+                Err(_) => unreachable!("custom value is not a {}", type_name::<T>()),
+            },
+            wrapper => unreachable!("{wrapper:?} is not a {}", type_name::<T>()),
+        }
+    }
+
+    // A checked add that overflows holds the overflow as its error.
+    pub fn add(&self, rhs: &Wrapper, overflow: Overflow) -> Self {
         match (self, rhs) {
-            (Wrapper::U64(lhs), Wrapper::U64(rhs)) => Wrapper::U64(lhs + rhs),
+            (Wrapper::U64(lhs), Wrapper::U64(rhs)) => overflow.add_u64(*lhs, *rhs).map_or_else(
+                || {
+                    let overflow = ArithmeticOverflow {
+                        left: *lhs,
+                        right: *rhs,
+                    };
+                    Wrapper::Err(Arc::new(Wrapper::Overflow(overflow)))
+                },
+                Wrapper::U64,
+            ),
+            (Wrapper::Missing, _) | (_, Wrapper::Missing) => Wrapper::Missing,
+            (lhs, rhs) => unreachable!("add is not defined for {lhs:?} and {rhs:?}"),
+        }
+    }
+}
+
+impl Debug for Wrapper {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Wrapper::U64(value) => f.debug_tuple("U64").field(value).finish(),
+            Wrapper::Str(value) => f.debug_tuple("Str").field(value).finish(),
+            Wrapper::Overflow(overflow) => f.debug_tuple("Overflow").field(overflow).finish(),
+            Wrapper::Custom(_) => f.write_str("Custom(..)"),
+            Wrapper::Missing => f.write_str("Missing"),
+            Wrapper::Err(error) => f.debug_tuple("Err").field(error).finish(),
+            Wrapper::Signal(signal) => write!(f, "Signal({})", signal.desc),
+            Wrapper::Map(collection) => f.debug_tuple("Map").field(collection).finish(),
+        }
+    }
+}
+
+impl PartialEq for Wrapper {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Wrapper::U64(lhs), Wrapper::U64(rhs)) => lhs == rhs,
+            (Wrapper::Str(lhs), Wrapper::Str(rhs)) => lhs == rhs,
+            (Wrapper::Overflow(lhs), Wrapper::Overflow(rhs)) => lhs == rhs,
+            (Wrapper::Custom(lhs), Wrapper::Custom(rhs)) => lhs.dyn_eq(&**rhs),
+            (Wrapper::Missing, Wrapper::Missing) => true,
+            (Wrapper::Err(lhs), Wrapper::Err(rhs)) => lhs == rhs,
+            (Wrapper::Signal(lhs), Wrapper::Signal(rhs)) => lhs == rhs,
+            (Wrapper::Map(lhs), Wrapper::Map(rhs)) => lhs == rhs,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Wrapper {}
+
+impl Hash for Wrapper {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Wrapper::U64(value) => value.hash(state),
+            Wrapper::Str(value) => value.hash(state),
+            Wrapper::Overflow(overflow) => overflow.hash(state),
+            Wrapper::Custom(value) => value.dyn_hash(state),
+            Wrapper::Missing => {}
+            Wrapper::Err(error) => error.hash(state),
+            Wrapper::Signal(signal) => signal.hash(state),
+            Wrapper::Map(collection) => collection.hash(state),
         }
     }
 }
@@ -0,0 +1,65 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    time::Duration,
+};
+
+// Which updates a time window keeps, as seen from the latest update of its signal. Windows are
+// only evaluated when their signal updates, so nothing expires while the signal is idle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Window {
+    // Updates that arrived less than `Duration` before the latest one, and the latest one itself,
+    // even for a zero `Duration`.
+    Lookback(Duration),
+    // Updates from the same `Duration` long period as the latest one, counted from the clock's
+    // epoch.
+    Period(Duration),
+}
+
+impl Window {
+    // Whether an update recorded at `then` is still in the window of an update at `now`.
+    pub fn contains(self, then: Duration, now: Duration) -> bool {
+        match self {
+            Window::Lookback(length) => {
+                now.saturating_sub(then) < length.max(Duration::from_nanos(1))
+            }
+            Window::Period(length) => {
+                let length = length.as_nanos().max(1);
+                then.as_nanos() / length == now.as_nanos() / length
+            }
+        }
+    }
+}
+
+impl From<Duration> for Window {
+    fn from(length: Duration) -> Self {
+        Window::Lookback(length)
+    }
+}
+
+impl Display for Window {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Window::Lookback(length) => write!(f, "lookback {length:?}"),
+            Window::Period(length) => write!(f, "period {length:?}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Aggregate {
+    // Wraps around on overflow, like `add`.
+    Sum,
+    // Rounded down, and zero for an empty window.
+    Avg,
+    Count,
+}
+
+impl Display for Aggregate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Aggregate::Sum => "sum",
+            Aggregate::Avg => "avg",
+            Aggregate::Count => "count",
+        })
+    }
+}
@@ -0,0 +1,772 @@
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    sync::Arc,
+    time::Duration,
+};
+
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{
+    observer::ArithmeticOverflow,
+    operators::{
+        collection::Collection,
+        registry,
+        types::{Type, Wrapper},
+        window::{Aggregate, Window},
+        Apt, Desc, Typed,
+    },
+};
+
+const MAGIC: &[u8; 4] = b"RRKS";
+pub const FORMAT_VERSION: u32 = 1;
+
+// Inputs are always written by name.
+const DESC_INPUT: u8 = 0;
+const DESC_ADD: u8 = 1;
+const DESC_SATURATING_ADD: u8 = 2;
+const DESC_CHECKED_ADD: u8 = 3;
+const DESC_OPTIONAL: u8 = 4;
+const DESC_UNWRAP_OR: u8 = 5;
+const DESC_ERRORS: u8 = 6;
+const DESC_OK_OR: u8 = 7;
+const DESC_SWITCH: u8 = 8;
+const DESC_COUNT: u8 = 9;
+const DESC_SUM: u8 = 10;
+const DESC_HOLD: u8 = 11;
+const DESC_CHANGES: u8 = 12;
+const DESC_SAMPLE: u8 = 13;
+const DESC_WINDOW: u8 = 14;
+const DESC_LAST_N: u8 = 15;
+
+const AGGREGATE_SUM: u8 = 0;
+const AGGREGATE_AVG: u8 = 1;
+const AGGREGATE_COUNT: u8 = 2;
+
+const WINDOW_LOOKBACK: u8 = 0;
+const WINDOW_PERIOD: u8 = 1;
+
+const TYPE_U64: u8 = 0;
+const TYPE_STR: u8 = 1;
+const TYPE_OPTION: u8 = 2;
+const TYPE_RESULT: u8 = 3;
+const TYPE_SIGNAL: u8 = 4;
+const TYPE_MAP: u8 = 5;
+const TYPE_OVERFLOW: u8 = 6;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SnapshotError {
+    BadMagic,
+    UnsupportedVersion(u32),
+    Truncated,
+    InvalidTag(u8),
+    InvalidReference(u64),
+    InvalidName,
+    InvalidString,
+    InvalidDuration,
+    // The length of a `last_n` that does not fit this platform's `usize`.
+    InvalidLength(u64),
+    // A node whose type does not follow from the types of its operands, or a signal value whose
+    // node has another type.
+    TypeMismatch(String),
+    // Snapshots are restored by input name, since ids depend on the order inputs were created in.
+    UnnamedInput,
+    Unpersistable(String),
+}
+
+impl Display for SnapshotError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::BadMagic => write!(f, "not an engine snapshot"),
+            SnapshotError::UnsupportedVersion(version) => {
+                write!(f, "unsupported snapshot version {version}")
+            }
+            SnapshotError::Truncated => write!(f, "snapshot is truncated"),
+            SnapshotError::InvalidTag(tag) => write!(f, "invalid tag {tag} in snapshot"),
+            SnapshotError::InvalidReference(id) => {
+                write!(f, "node refers to node {id} which is not defined before it")
+            }
+            SnapshotError::InvalidName => write!(f, "input name is not valid UTF-8"),
+            SnapshotError::InvalidString => write!(f, "string value is not valid UTF-8"),
+            SnapshotError::InvalidDuration => write!(f, "duration has too many nanoseconds"),
+            SnapshotError::InvalidLength(n) => write!(f, "length {n} is too large"),
+            SnapshotError::TypeMismatch(node) => write!(f, "{node} has the wrong type"),
+            SnapshotError::UnnamedInput => write!(f, "only named inputs can be persisted"),
+            SnapshotError::Unpersistable(node) => write!(f, "{node} cannot be persisted"),
+        }
+    }
+}
+
+impl Error for SnapshotError {}
+
+pub struct SnapshotNode {
+    pub signal: Apt,
+    pub value: Wrapper,
+    // Only window nodes have one.
+    pub history: WindowHistory,
+}
+
+// What a window remembers besides its value: the values it still holds, oldest first, along with
+// when each arrived, and how many values it ever recorded.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct WindowHistory {
+    pub values: Vec<(Duration, Wrapper)>,
+    pub recorded: u64,
+}
+
+// Nodes are kept in registration order, so every node comes after the nodes it depends on.
+#[derive(Default)]
+pub struct Snapshot {
+    pub nodes: Vec<SnapshotNode>,
+}
+
+impl Snapshot {
+    /// # Errors
+    ///
+    /// Fails when a node reads an unnamed input, or holds state that cannot be written.
+    pub fn encode(&self) -> Result<Vec<u8>, SnapshotError> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+
+        let mut rebuilt = FxHashSet::<&Apt>::default();
+        let mut nodes = Vec::new();
+        for node in &self.nodes {
+            let desc = &node.signal.desc;
+            let persistence = if desc
+                .operands()
+                .into_iter()
+                .any(|operand| rebuilt.contains(operand))
+            {
+                // Rebuilding would start the node over, which only stateless nodes survive.
+                if keeps_state(desc) {
+                    Persistence::Unsupported
+                } else {
+                    Persistence::Rebuilt
+                }
+            } else {
+                persistence(&node.signal)
+            };
+            match persistence {
+                Persistence::Stored => nodes.push(node),
+                Persistence::Rebuilt => {
+                    rebuilt.insert(&node.signal);
+                }
+                Persistence::Unsupported => {
+                    return Err(SnapshotError::Unpersistable(node.signal.to_string()))
+                }
+            }
+        }
+        write_u64(&mut out, nodes.len() as u64);
+
+        // Values come after every node, so signals held as values can refer to any of them.
+        let mut positions = FxHashMap::<Apt, u64>::default();
+        for (pos, node) in nodes.iter().enumerate() {
+            let Typed { desc, rtype } = &**node.signal;
+            encode_desc(&mut out, desc, *rtype, &positions)?;
+            encode_type(&mut out, *rtype);
+            positions.insert(Arc::clone(&node.signal), pos as u64);
+        }
+        for SnapshotNode {
+            signal,
+            value,
+            history,
+        } in nodes
+        {
+            encode_value(&mut out, signal.rtype, value, &positions)?;
+            if let Desc::Window(inner, ..) | Desc::LastN(inner, _) = &signal.desc {
+                encode_history(&mut out, inner.rtype, history, &positions)?;
+            }
+        }
+        Ok(out)
+    }
+
+    /// # Errors
+    ///
+    /// Fails when the bytes are not a well-formed snapshot of this format version.
+    pub fn decode(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        let mut reader = Reader { bytes };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(SnapshotError::BadMagic);
+        }
+        let version = u32::from_le_bytes(reader.array()?);
+        if version != FORMAT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+
+        let count = reader.u64()?;
+        let mut nodes = Vec::<SnapshotNode>::new();
+        for _ in 0..count {
+            let desc = match reader.u8()? {
+                DESC_INPUT => Desc::Input(registry::lookup_or_claim(reader.str()?)),
+                DESC_ADD => {
+                    let left = reader.node(&nodes)?;
+                    let right = reader.node(&nodes)?;
+                    Desc::Add(left, right)
+                }
+                DESC_SATURATING_ADD => {
+                    let left = reader.node(&nodes)?;
+                    let right = reader.node(&nodes)?;
+                    Desc::SaturatingAdd(left, right)
+                }
+                DESC_CHECKED_ADD => {
+                    let left = reader.node(&nodes)?;
+                    let right = reader.node(&nodes)?;
+                    Desc::CheckedAdd(left, right)
+                }
+                DESC_OPTIONAL => Desc::Optional(reader.node(&nodes)?),
+                DESC_UNWRAP_OR => {
+                    let inner = reader.node(&nodes)?;
+                    let Type::Option(rtype) = inner.rtype else {
+                        return Err(SnapshotError::TypeMismatch(format!(
+                            "unwrap_or of {}",
+                            **inner
+                        )));
+                    };
+                    let default = decode_value(&mut reader, *rtype, &nodes)?;
+                    Desc::UnwrapOr(inner, default)
+                }
+                DESC_ERRORS => Desc::Errors(reader.node(&nodes)?),
+                DESC_OK_OR => {
+                    let inner = reader.node(&nodes)?;
+                    let error_type = decode_type(&mut reader)?;
+                    let error = decode_value(&mut reader, error_type, &nodes)?;
+                    Desc::OkOr(inner, error)
+                }
+                DESC_SWITCH => Desc::Switch(reader.node(&nodes)?),
+                DESC_COUNT => Desc::Count(reader.node(&nodes)?),
+                DESC_SUM => Desc::Sum(reader.node(&nodes)?),
+                DESC_HOLD => {
+                    let inner = reader.node(&nodes)?;
+                    let init = decode_value(&mut reader, inner.rtype, &nodes)?;
+                    Desc::Hold(inner, init)
+                }
+                DESC_CHANGES => Desc::Changes(reader.node(&nodes)?),
+                DESC_SAMPLE => {
+                    let event = reader.node(&nodes)?;
+                    let signal = reader.node(&nodes)?;
+                    Desc::Sample(event, signal)
+                }
+                DESC_WINDOW => {
+                    let inner = reader.node(&nodes)?;
+                    let aggregate = match reader.u8()? {
+                        AGGREGATE_SUM => Aggregate::Sum,
+                        AGGREGATE_AVG => Aggregate::Avg,
+                        AGGREGATE_COUNT => Aggregate::Count,
+                        tag => return Err(SnapshotError::InvalidTag(tag)),
+                    };
+                    Desc::Window(inner, aggregate, reader.window()?)
+                }
+                DESC_LAST_N => {
+                    let inner = reader.node(&nodes)?;
+                    let n = reader.u64()?;
+                    let n = usize::try_from(n).map_err(|_| SnapshotError::InvalidLength(n))?;
+                    Desc::LastN(inner, n)
+                }
+                tag => return Err(SnapshotError::InvalidTag(tag)),
+            };
+            let rtype = decode_type(&mut reader)?;
+            let typed = Typed { desc, rtype };
+            // Engines trust the types of the nodes they register.
+            if !types_match(&typed) {
+                return Err(SnapshotError::TypeMismatch(typed.to_string()));
+            }
+            nodes.push(SnapshotNode {
+                signal: Arc::new(typed.into()),
+                value: Wrapper::Missing,
+                history: WindowHistory::default(),
+            });
+        }
+        for pos in 0..nodes.len() {
+            let signal = Arc::clone(&nodes[pos].signal);
+            nodes[pos].value = decode_value(&mut reader, signal.rtype, &nodes)?;
+            if let Desc::Window(inner, ..) | Desc::LastN(inner, _) = &signal.desc {
+                nodes[pos].history = decode_history(&mut reader, inner.rtype, &nodes)?;
+            }
+        }
+        Ok(Self { nodes })
+    }
+}
+
+// What a snapshot keeps of a node. Every operator has to pick one, so none of them loses its
+// state without saying so.
+enum Persistence {
+    // Written along with its value.
+    Stored,
+    // Left out along with the stateless nodes built on it, and rebuilt from its operands when it
+    // is listened to again. Only for nodes whose closures cannot be written, and whose state
+    // follows from the state of their operands.
+    Rebuilt,
+    // Keeps state that cannot be written, or that would be lost by rebuilding it, so the snapshot
+    // fails.
+    Unsupported,
+}
+
+// Whether the node remembers more than the current values of its operands.
+fn keeps_state(desc: &Desc) -> bool {
+    matches!(
+        desc,
+        Desc::Hold(..) | Desc::Sample(..) | Desc::Window(..) | Desc::LastN(..)
+    )
+}
+
+fn persistence(typed: &Typed) -> Persistence {
+    match typed.desc {
+        Desc::Input(_)
+        | Desc::Add(..)
+        | Desc::SaturatingAdd(..)
+        | Desc::CheckedAdd(..)
+        | Desc::Optional(_)
+        | Desc::UnwrapOr(..)
+        | Desc::Errors(_)
+        | Desc::OkOr(..)
+        | Desc::Switch(_)
+        | Desc::Count(_)
+        | Desc::Sum(_)
+        | Desc::Hold(..)
+        | Desc::Changes(_)
+        | Desc::Sample(..) => {
+            // Custom values are opaque to the engine.
+            if typed.rtype.holds_custom() {
+                Persistence::Unsupported
+            } else {
+                Persistence::Stored
+            }
+        }
+        Desc::MapErr(..)
+        | Desc::Recover(..)
+        | Desc::Filter(..)
+        | Desc::Map(..)
+        | Desc::GroupBy(..) => Persistence::Rebuilt,
+        // Histories hold values of the windowed signal.
+        Desc::Window(ref inner, ..) | Desc::LastN(ref inner, _) => {
+            if typed.rtype.holds_custom() || inner.rtype.holds_custom() {
+                Persistence::Unsupported
+            } else {
+                Persistence::Stored
+            }
+        }
+    }
+}
+
+// Whether the node has the type its operator gives its operands' types, as checked by the
+// operator's constructor for nodes built in code.
+fn types_match(typed: &Typed) -> bool {
+    let rtype = typed.rtype;
+    match &typed.desc {
+        Desc::Input(_) => true,
+        Desc::Add(left, right) | Desc::SaturatingAdd(left, right) => {
+            left.rtype == Type::U64 && right.rtype == Type::U64 && rtype == Type::U64
+        }
+        Desc::CheckedAdd(left, right) => {
+            left.rtype == Type::U64
+                && right.rtype == Type::U64
+                && rtype == Type::result(Type::U64, Type::Overflow)
+        }
+        Desc::Optional(inner) => rtype == Type::option(inner.rtype),
+        Desc::UnwrapOr(inner, _) => inner.rtype == Type::option(rtype),
+        Desc::Errors(inner) => matches!(inner.rtype, Type::Result(_, error) if *error == rtype),
+        Desc::OkOr(inner, _) => match (inner.rtype, rtype) {
+            (Type::Option(inner), Type::Result(ok, _)) => inner == ok,
+            _ => false,
+        },
+        Desc::Switch(inner) => inner.rtype == Type::signal(rtype),
+        Desc::Count(inner) => matches!(inner.rtype, Type::Map(..)) && rtype == Type::U64,
+        Desc::Sum(inner) => matches!(inner.rtype, Type::Map(_, Type::U64)) && rtype == Type::U64,
+        Desc::Hold(inner, _) | Desc::Changes(inner) | Desc::Sample(_, inner) => {
+            inner.rtype == rtype
+        }
+        Desc::Window(inner, aggregate, _) => {
+            rtype == Type::U64 && (*aggregate == Aggregate::Count || inner.rtype == Type::U64)
+        }
+        Desc::LastN(inner, _) => rtype == Type::map(Type::U64, inner.rtype),
+        Desc::MapErr(..)
+        | Desc::Recover(..)
+        | Desc::Filter(..)
+        | Desc::Map(..)
+        | Desc::GroupBy(..) => unreachable!("{} is not stored", typed.desc),
+    }
+}
+
+// Every node refers to its operands by their position, so operands are always encoded first.
+fn encode_desc(
+    out: &mut Vec<u8>,
+    desc: &Desc,
+    rtype: Type,
+    positions: &FxHashMap<Apt, u64>,
+) -> Result<(), SnapshotError> {
+    match desc {
+        Desc::Input(input) => {
+            let name = input.name().ok_or(SnapshotError::UnnamedInput)?;
+            out.push(DESC_INPUT);
+            write_u64(out, name.len() as u64);
+            out.extend_from_slice(name.as_bytes());
+        }
+        Desc::Add(left, right) => {
+            out.push(DESC_ADD);
+            write_u64(out, positions[left]);
+            write_u64(out, positions[right]);
+        }
+        Desc::SaturatingAdd(left, right) => {
+            out.push(DESC_SATURATING_ADD);
+            write_u64(out, positions[left]);
+            write_u64(out, positions[right]);
+        }
+        Desc::CheckedAdd(left, right) => {
+            out.push(DESC_CHECKED_ADD);
+            write_u64(out, positions[left]);
+            write_u64(out, positions[right]);
+        }
+        Desc::Optional(inner) => {
+            out.push(DESC_OPTIONAL);
+            write_u64(out, positions[inner]);
+        }
+        Desc::UnwrapOr(inner, default) => {
+            out.push(DESC_UNWRAP_OR);
+            write_u64(out, positions[inner]);
+            encode_value(out, rtype, default, positions)?;
+        }
+        Desc::Errors(inner) => {
+            out.push(DESC_ERRORS);
+            write_u64(out, positions[inner]);
+        }
+        Desc::OkOr(inner, error) => {
+            let Type::Result(_, error_type) = rtype else {
+                unreachable!("ok_or always has a result type")
+            };
+            out.push(DESC_OK_OR);
+            write_u64(out, positions[inner]);
+            encode_type(out, *error_type);
+            encode_value(out, *error_type, error, positions)?;
+        }
+        Desc::Switch(inner) => {
+            out.push(DESC_SWITCH);
+            write_u64(out, positions[inner]);
+        }
+        Desc::Count(inner) => {
+            out.push(DESC_COUNT);
+            write_u64(out, positions[inner]);
+        }
+        Desc::Sum(inner) => {
+            out.push(DESC_SUM);
+            write_u64(out, positions[inner]);
+        }
+        Desc::Hold(inner, init) => {
+            out.push(DESC_HOLD);
+            write_u64(out, positions[inner]);
+            encode_value(out, rtype, init, positions)?;
+        }
+        Desc::Changes(inner) => {
+            out.push(DESC_CHANGES);
+            write_u64(out, positions[inner]);
+        }
+        Desc::Sample(event, signal) => {
+            out.push(DESC_SAMPLE);
+            write_u64(out, positions[event]);
+            write_u64(out, positions[signal]);
+        }
+        Desc::Window(inner, aggregate, window) => {
+            out.push(DESC_WINDOW);
+            write_u64(out, positions[inner]);
+            out.push(match aggregate {
+                Aggregate::Sum => AGGREGATE_SUM,
+                Aggregate::Avg => AGGREGATE_AVG,
+                Aggregate::Count => AGGREGATE_COUNT,
+            });
+            encode_window(out, *window);
+        }
+        Desc::LastN(inner, n) => {
+            out.push(DESC_LAST_N);
+            write_u64(out, positions[inner]);
+            write_u64(out, *n as u64);
+        }
+        Desc::MapErr(..)
+        | Desc::Recover(..)
+        | Desc::Filter(..)
+        | Desc::Map(..)
+        | Desc::GroupBy(..) => unreachable!("{desc} is not stored"),
+    }
+    Ok(())
+}
+
+fn write_u64(out: &mut Vec<u8>, value: u64) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+// Whole seconds, then the nanoseconds left over.
+fn write_duration(out: &mut Vec<u8>, value: Duration) {
+    write_u64(out, value.as_secs());
+    write_u64(out, u64::from(value.subsec_nanos()));
+}
+
+fn encode_window(out: &mut Vec<u8>, window: Window) {
+    match window {
+        Window::Lookback(length) => {
+            out.push(WINDOW_LOOKBACK);
+            write_duration(out, length);
+        }
+        Window::Period(length) => {
+            out.push(WINDOW_PERIOD);
+            write_duration(out, length);
+        }
+    }
+}
+
+fn encode_history(
+    out: &mut Vec<u8>,
+    rtype: Type,
+    history: &WindowHistory,
+    positions: &FxHashMap<Apt, u64>,
+) -> Result<(), SnapshotError> {
+    write_u64(out, history.values.len() as u64);
+    for (arrived, value) in &history.values {
+        write_duration(out, *arrived);
+        encode_value(out, rtype, value, positions)?;
+    }
+    write_u64(out, history.recorded);
+    Ok(())
+}
+
+fn decode_history(
+    reader: &mut Reader,
+    rtype: Type,
+    nodes: &[SnapshotNode],
+) -> Result<WindowHistory, SnapshotError> {
+    let count = reader.u64()?;
+    let mut values = Vec::new();
+    for _ in 0..count {
+        let arrived = reader.duration()?;
+        values.push((arrived, decode_value(reader, rtype, nodes)?));
+    }
+    let recorded = reader.u64()?;
+    Ok(WindowHistory { values, recorded })
+}
+
+fn encode_type(out: &mut Vec<u8>, rtype: Type) {
+    match rtype {
+        Type::U64 => out.push(TYPE_U64),
+        Type::Str => out.push(TYPE_STR),
+        Type::Overflow => out.push(TYPE_OVERFLOW),
+        Type::Custom(_) => unreachable!("custom types are not stored"),
+        Type::Option(inner) => {
+            out.push(TYPE_OPTION);
+            encode_type(out, *inner);
+        }
+        Type::Result(ok, err) => {
+            out.push(TYPE_RESULT);
+            encode_type(out, *ok);
+            encode_type(out, *err);
+        }
+        Type::Signal(inner) => {
+            out.push(TYPE_SIGNAL);
+            encode_type(out, *inner);
+        }
+        Type::Map(key, value) => {
+            out.push(TYPE_MAP);
+            encode_type(out, *key);
+            encode_type(out, *value);
+        }
+    }
+}
+
+fn decode_type(reader: &mut Reader) -> Result<Type, SnapshotError> {
+    match reader.u8()? {
+        TYPE_U64 => Ok(Type::U64),
+        TYPE_STR => Ok(Type::Str),
+        TYPE_OPTION => decode_type(reader).map(Type::option),
+        TYPE_RESULT => Ok(Type::result(decode_type(reader)?, decode_type(reader)?)),
+        TYPE_SIGNAL => decode_type(reader).map(Type::signal),
+        TYPE_MAP => Ok(Type::map(decode_type(reader)?, decode_type(reader)?)),
+        TYPE_OVERFLOW => Ok(Type::Overflow),
+        tag => Err(SnapshotError::InvalidTag(tag)),
+    }
+}
+
+// Every value starts with a byte telling whether it is present.
+fn encode_value(
+    out: &mut Vec<u8>,
+    rtype: Type,
+    value: &Wrapper,
+    positions: &FxHashMap<Apt, u64>,
+) -> Result<(), SnapshotError> {
+    out.push(u8::from(*value != Wrapper::Missing));
+    if *value != Wrapper::Missing {
+        encode_payload(out, rtype, value, positions)?;
+    }
+    Ok(())
+}
+
+fn encode_payload(
+    out: &mut Vec<u8>,
+    rtype: Type,
+    value: &Wrapper,
+    positions: &FxHashMap<Apt, u64>,
+) -> Result<(), SnapshotError> {
+    match (rtype, value) {
+        // Results start with a byte telling whether they failed.
+        (Type::Result(_, err), Wrapper::Err(error)) => {
+            out.push(1);
+            encode_value(out, *err, error, positions)?;
+        }
+        (Type::Result(ok, _), value) => {
+            out.push(0);
+            encode_payload(out, *ok, value, positions)?;
+        }
+        (Type::Option(inner), value) => encode_payload(out, *inner, value, positions)?,
+        // Maps store their entries, and leave out the changes of their latest update. Entries are
+        // sorted by their encoding, since maps do not keep them in any particular order.
+        (Type::Map(key_type, value_type), Wrapper::Map(collection)) => {
+            let mut entries = Vec::new();
+            for (key, value) in &collection.entries() {
+                let mut entry = Vec::new();
+                encode_value(&mut entry, *key_type, key, positions)?;
+                encode_value(&mut entry, *value_type, value, positions)?;
+                entries.push(entry);
+            }
+            entries.sort_unstable();
+            write_u64(out, entries.len() as u64);
+            for entry in entries {
+                out.extend(entry);
+            }
+        }
+        // Signals are stored as the position of their node, so they have to be stored as well.
+        (_, Wrapper::Signal(signal)) => {
+            let pos = positions
+                .get(signal)
+                .ok_or_else(|| SnapshotError::Unpersistable(signal.to_string()))?;
+            write_u64(out, *pos);
+        }
+        (_, Wrapper::U64(value)) => write_u64(out, *value),
+        (_, Wrapper::Str(value)) => {
+            write_u64(out, value.len() as u64);
+            out.extend_from_slice(value.as_bytes());
+        }
+        (_, Wrapper::Overflow(overflow)) => {
+            write_u64(out, overflow.left);
+            write_u64(out, overflow.right);
+        }
+        (_, Wrapper::Custom(_) | Wrapper::Missing | Wrapper::Err(_) | Wrapper::Map(_)) => {}
+    }
+    Ok(())
+}
+
+fn decode_value(
+    reader: &mut Reader,
+    rtype: Type,
+    nodes: &[SnapshotNode],
+) -> Result<Wrapper, SnapshotError> {
+    if reader.u8()? == 0 {
+        return Ok(Wrapper::Missing);
+    }
+    decode_payload(reader, rtype, nodes)
+}
+
+fn decode_payload(
+    reader: &mut Reader,
+    rtype: Type,
+    nodes: &[SnapshotNode],
+) -> Result<Wrapper, SnapshotError> {
+    match rtype {
+        Type::U64 => reader.u64().map(Wrapper::U64),
+        Type::Str => {
+            let len = usize::try_from(reader.u64()?).map_err(|_| SnapshotError::Truncated)?;
+            let value =
+                std::str::from_utf8(reader.take(len)?).map_err(|_| SnapshotError::InvalidString)?;
+            Ok(Wrapper::Str(Arc::from(value)))
+        }
+        Type::Overflow => Ok(Wrapper::Overflow(ArithmeticOverflow {
+            left: reader.u64()?,
+            right: reader.u64()?,
+        })),
+        Type::Custom(_) => unreachable!("custom types are not stored"),
+        Type::Signal(inner) => {
+            let signal = reader.node(nodes)?;
+            if signal.rtype != *inner {
+                return Err(SnapshotError::TypeMismatch(format!(
+                    "signal value {}",
+                    **signal
+                )));
+            }
+            Ok(Wrapper::Signal(signal))
+        }
+        // `Some` values are stored like values of the inner type.
+        Type::Option(inner) => decode_payload(reader, *inner, nodes),
+        Type::Result(ok, err) => match reader.u8()? {
+            0 => decode_payload(reader, *ok, nodes),
+            1 => {
+                let error = decode_value(reader, *err, nodes)?;
+                Ok(Wrapper::Err(Arc::new(error)))
+            }
+            tag => Err(SnapshotError::InvalidTag(tag)),
+        },
+        Type::Map(key_type, value_type) => {
+            let mut entries = Vec::new();
+            for _ in 0..reader.u64()? {
+                let key = decode_value(reader, *key_type, nodes)?;
+                let value = decode_value(reader, *value_type, nodes)?;
+                entries.push((key, value));
+            }
+            Ok(Wrapper::Map(Arc::new(Collection::from_entries(entries))))
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SnapshotError> {
+        if self.bytes.len() < len {
+            return Err(SnapshotError::Truncated);
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], SnapshotError> {
+        let mut res = [0; N];
+        res.copy_from_slice(self.take(N)?);
+        Ok(res)
+    }
+
+    fn u8(&mut self) -> Result<u8, SnapshotError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u64(&mut self) -> Result<u64, SnapshotError> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    fn duration(&mut self) -> Result<Duration, SnapshotError> {
+        let secs = self.u64()?;
+        let nanos = u32::try_from(self.u64()?)
+            .ok()
+            .filter(|nanos| *nanos < 1_000_000_000)
+            .ok_or(SnapshotError::InvalidDuration)?;
+        Ok(Duration::new(secs, nanos))
+    }
+
+    fn window(&mut self) -> Result<Window, SnapshotError> {
+        match self.u8()? {
+            WINDOW_LOOKBACK => self.duration().map(Window::Lookback),
+            WINDOW_PERIOD => self.duration().map(Window::Period),
+            tag => Err(SnapshotError::InvalidTag(tag)),
+        }
+    }
+
+    fn str(&mut self) -> Result<&'a str, SnapshotError> {
+        let len = usize::try_from(self.u64()?).map_err(|_| SnapshotError::Truncated)?;
+        std::str::from_utf8(self.take(len)?).map_err(|_| SnapshotError::InvalidName)
+    }
+
+    fn node(&mut self, nodes: &[SnapshotNode]) -> Result<Apt, SnapshotError> {
+        let id = self.u64()?;
+        usize::try_from(id)
+            .ok()
+            .and_then(|pos| nodes.get(pos))
+            .map(|node| Arc::clone(&node.signal))
+            .ok_or(SnapshotError::InvalidReference(id))
+    }
+}
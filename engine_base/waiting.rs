@@ -1,5 +1,6 @@
+use crossbeam_channel::Receiver;
 use crossbeam_utils::sync::{Parker, Unparker};
-use std::thread::JoinHandle;
+use std::{marker::PhantomData, thread::JoinHandle};
 
 #[must_use]
 pub trait Waiting<T> {
@@ -50,3 +51,85 @@ impl<T> Waiting<T> for ThreadJoinWaiting<T> {
         self.0.join().unwrap()
     }
 }
+
+pub struct ReplyWaiting<T>(Receiver<T>);
+
+impl<T> From<Receiver<T>> for ReplyWaiting<T> {
+    fn from(receiver: Receiver<T>) -> Self {
+        ReplyWaiting(receiver)
+    }
+}
+
+impl<T> Waiting<T> for ReplyWaiting<T> {
+    fn wait(self) -> T {
+        self.0.recv().expect("Engine thread is dead")
+    }
+}
+
+pub struct MapWaiting<W, T, F> {
+    waiting: W,
+    f: F,
+    value: PhantomData<T>,
+}
+
+impl<W, T, F> MapWaiting<W, T, F> {
+    pub fn new(waiting: W, f: F) -> Self {
+        MapWaiting {
+            waiting,
+            f,
+            value: PhantomData,
+        }
+    }
+}
+
+impl<W: Waiting<T>, T, U, F: FnOnce(T) -> U> Waiting<U> for MapWaiting<W, T, F> {
+    fn wait(self) -> U {
+        (self.f)(self.waiting.wait())
+    }
+}
+
+impl<W: MaybeWaiting<T>, T, U, F: FnOnce(T) -> U> MaybeWaiting<U> for MapWaiting<W, T, F> {
+    fn immediate(self) -> U {
+        (self.f)(self.waiting.immediate())
+    }
+}
+
+pub struct AllWaiting<W>(Vec<W>);
+
+impl<W> From<Vec<W>> for AllWaiting<W> {
+    fn from(waiting: Vec<W>) -> Self {
+        AllWaiting(waiting)
+    }
+}
+
+impl<W: Waiting<T>, T> Waiting<Vec<T>> for AllWaiting<W> {
+    fn wait(self) -> Vec<T> {
+        self.0.into_iter().map(Waiting::wait).collect()
+    }
+}
+
+impl<W: MaybeWaiting<T>, T> MaybeWaiting<Vec<T>> for AllWaiting<W> {
+    fn immediate(self) -> Vec<T> {
+        self.0.into_iter().map(MaybeWaiting::immediate).collect()
+    }
+}
+
+pub struct ReadyWaiting<T>(T);
+
+impl<T> From<T> for ReadyWaiting<T> {
+    fn from(value: T) -> Self {
+        ReadyWaiting(value)
+    }
+}
+
+impl<T> Waiting<T> for ReadyWaiting<T> {
+    fn wait(self) -> T {
+        self.0
+    }
+}
+
+impl<T> MaybeWaiting<T> for ReadyWaiting<T> {
+    fn immediate(self) -> T {
+        self.0
+    }
+}
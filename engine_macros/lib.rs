@@ -0,0 +1,52 @@
+use proc_macro::TokenStream;
+
+use quote::quote;
+use syn::{parse_macro_input, DeriveInput, LitStr};
+
+// Lets a type flow through signals as a custom value. The type has to be `Clone`, for listeners
+// that cannot take the shared value, `Default`, for inputs that were never emitted, and `Eq` and
+// `Hash`, so updates that leave it as it was can be told apart and it can key maps.
+//
+// The type is described by its path, or by the name given with `#[rtype(name = "...")]`.
+#[proc_macro_derive(RType, attributes(rtype))]
+pub fn derive_rtype(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+    let mut type_name = None;
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("rtype")) {
+        let parsed = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                type_name = Some(meta.value()?.parse::<LitStr>()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `name = \"...\"`"))
+            }
+        });
+        if let Err(error) = parsed {
+            return error.to_compile_error().into();
+        }
+    }
+    let type_name = type_name.map_or_else(
+        || quote! { ::std::concat!(::std::module_path!(), "::", ::std::stringify!(#name)) },
+        |type_name| quote! { #type_name },
+    );
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    quote! {
+        impl #impl_generics ::engine_base::operators::types::RType for #name #ty_generics
+        #where_clause
+        {
+            fn into_type() -> ::engine_base::operators::types::Type {
+                ::engine_base::operators::types::Type::custom::<Self>(#type_name)
+            }
+
+            fn coerce(wrapper: ::engine_base::operators::types::Wrapper) -> Self {
+                wrapper.downcast::<Self>()
+            }
+
+            fn wrap(self) -> ::engine_base::operators::types::Wrapper {
+                ::engine_base::operators::types::Wrapper::custom(self)
+            }
+        }
+    }
+    .into()
+}
@@ -1,22 +1,35 @@
 use std::fmt::{self, Debug, Formatter};
 
+use crossbeam_channel::Sender;
 use crossbeam_utils::sync::Unparker;
-use engine_base::operators::{
-    types::{Type, Wrapper},
-    InputRef,
+use engine_base::{
+    describe::GraphDescription,
+    observer::CommandKind,
+    operators::{
+        types::{Type, Wrapper},
+        InputRef,
+    },
+    snapshot::SnapshotError,
+    ListenOptions,
 };
 
 use crate::{
+    internal::Node,
     transport::{Emitter, Listener},
     Apt,
 };
 
 pub enum Command {
     Start(Unparker),
-    Shutdown,
+    Pause(Unparker),
+    Resume(Unparker),
+    Shutdown {
+        drain: bool,
+    },
     Listen {
         signal: Apt,
         listener: Box<dyn Listener + Send>,
+        options: ListenOptions,
         unparker: Unparker,
     },
     Emit {
@@ -25,22 +38,60 @@ pub enum Command {
         emitter: Box<dyn Emitter + Send>,
         unparker: Unparker,
     },
+    Describe(Sender<GraphDescription>),
+    Snapshot(Sender<Result<Vec<u8>, SnapshotError>>),
+    Detach(Sender<Detached>),
+    Absorb {
+        detached: Detached,
+        unparker: Unparker,
+    },
+}
+
+impl Command {
+    pub fn kind(&self) -> CommandKind {
+        match self {
+            Command::Start(_) => CommandKind::Start,
+            Command::Pause(_) => CommandKind::Pause,
+            Command::Resume(_) => CommandKind::Resume,
+            Command::Shutdown { .. } => CommandKind::Shutdown,
+            Command::Listen { .. } => CommandKind::Listen,
+            Command::Emit { .. } => CommandKind::Emit,
+            Command::Describe(_) => CommandKind::Describe,
+            Command::Snapshot(_) => CommandKind::Snapshot,
+            Command::Detach(_) => CommandKind::Detach,
+            Command::Absorb { .. } => CommandKind::Absorb,
+        }
+    }
 }
 
 impl Debug for Command {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             Command::Start(_) => write!(f, "Start")?,
-            Command::Shutdown => write!(f, "Shutdown")?,
+            Command::Pause(_) => write!(f, "Pause")?,
+            Command::Resume(_) => write!(f, "Resume")?,
+            Command::Shutdown { drain } => write!(f, "Shutdown(drain: {drain})")?,
             Command::Listen {
-                signal, listener, ..
-            } => write!(f, "Listen({signal:?}, {listener:p})")?,
+                signal,
+                listener,
+                options,
+                ..
+            } => write!(f, "Listen({signal:?}, {listener:p}, {options:?})")?,
             Command::Emit {
                 input,
                 rtype,
                 emitter,
                 ..
             } => write!(f, "Emit({input:?}, {rtype:?}, {emitter:p})")?,
+            Command::Describe(_) => write!(f, "Describe")?,
+            Command::Snapshot(_) => write!(f, "Snapshot")?,
+            Command::Detach(_) => write!(f, "Detach")?,
+            Command::Absorb { detached, .. } => write!(
+                f,
+                "Absorb({} nodes, {} emitters)",
+                detached.nodes.len(),
+                detached.emitters.len()
+            )?,
         }
         Ok(())
     }
@@ -51,3 +102,19 @@ pub struct Update {
     pub input_pos: usize,
     pub value: Wrapper,
 }
+
+pub struct DetachedNode {
+    pub signal: Apt,
+    pub value: Wrapper,
+    pub node: Node,
+    pub listeners: Vec<Box<dyn Listener + Send>>,
+}
+
+// Everything an engine thread owns, handed over so another thread can take over its graph.
+// Emitters and queued updates refer to nodes by their position in `nodes`.
+pub struct Detached {
+    pub nodes: Vec<DetachedNode>,
+    pub emitters: Vec<(usize, Box<dyn Emitter + Send>)>,
+    pub queued: Vec<Update>,
+    pub processed: usize,
+}
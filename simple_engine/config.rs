@@ -0,0 +1,41 @@
+use std::sync::Arc;
+
+use engine_base::{
+    clock::{Clock, SystemClock},
+    observer::{EngineObserver, NoopObserver},
+};
+
+use crate::{queue::QueueConfig, tape::Evaluator};
+
+// What input fields hold before their first emission.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Uninitialized {
+    // The zero value of the input's type. Optional inputs start out as `None` either way.
+    #[default]
+    Zeroed,
+    // A missing value, which operators propagate and only optional listeners observe.
+    Missing,
+}
+
+#[derive(Clone)]
+pub struct EngineConfig {
+    pub observer: Arc<dyn EngineObserver>,
+    pub prestart_queue: QueueConfig,
+    pub pause_queue: QueueConfig,
+    pub evaluator: Evaluator,
+    pub uninitialized: Uninitialized,
+    pub clock: Arc<dyn Clock>,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        Self {
+            observer: Arc::new(NoopObserver),
+            prestart_queue: QueueConfig::default(),
+            pause_queue: QueueConfig::default(),
+            evaluator: Evaluator::default(),
+            uninitialized: Uninitialized::default(),
+            clock: Arc::new(SystemClock),
+        }
+    }
+}
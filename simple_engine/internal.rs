@@ -1,227 +1,1015 @@
-use std::{collections::VecDeque, sync::Arc};
-
-use crossbeam_channel::{Receiver, RecvError, Select, SelectedOperation};
-use crossbeam_utils::sync::Unparker;
-use engine_base::operators::{
-    types::Wrapper,
-    Desc::{Add, Input},
-    InputRef, Typed,
+use std::{collections::BTreeSet, sync::Arc, time::Instant};
+
+use crossbeam_channel::{Receiver, RecvError, Select, SelectedOperation, TryRecvError};
+use engine_base::{
+    clock::Clock,
+    describe::{GraphDescription, NodeDescription},
+    lifecycle::{Lifecycle, SharedLifecycle},
+    observer::{EngineObserver, Propagation, QueueKind},
+    operators::{
+        collection::{Collection, Delta},
+        types::{Overflow, Type, Wrapper},
+        Desc,
+        Desc::{
+            Add, Changes, CheckedAdd, Count, Errors, Filter, GroupBy, Hold, Input, LastN, Map,
+            MapErr, OkOr, Optional, Recover, Sample, SaturatingAdd, Sum, Switch, UnwrapOr, Window,
+        },
+        Function, InputRef, Predicate, Typed,
+    },
+    snapshot::{Snapshot, SnapshotError, SnapshotNode, WindowHistory},
+    ListenOptions,
 };
 use rustc_hash::FxHashMap;
 use typed_arena::Arena;
 
 use crate::{
-    commands::{Command, Update},
-    transport::{Emitter, Listener},
+    commands::{Command, Detached, DetachedNode, Update},
+    config::{EngineConfig, Uninitialized},
+    propagate::{selected, Graph, Propagator},
+    queue::UpdateQueue,
+    tape::Fields,
+    transport::{Emitter, Gate, Listener},
+    window::Buffer,
     Apt,
 };
 
 type RecvResult<T> = Result<T, RecvError>;
 
+#[derive(Clone)]
+pub enum Node {
+    Input,
+    Add(Overflow, usize, usize),
+    Optional(usize),
+    UnwrapOr(usize, Wrapper),
+    MapErr(usize, Function),
+    Recover(usize, Function),
+    Errors(usize),
+    OkOr(usize, Wrapper),
+    // The selector, and the node it currently points at.
+    Switch(usize, Option<usize>),
+    // Collection nodes read their own previous value, and only apply the changes of their source.
+    Filter(usize, Predicate),
+    Map(usize, Function),
+    GroupBy(usize, Function),
+    Count(usize),
+    Sum(usize),
+    // Records every update of its source, so it must only be evaluated when the source updates.
+    Window(usize, Arc<Buffer>),
+    // Event nodes only run when their event occurs.
+    Hold(usize),
+    Changes(usize),
+    // The event, and the signal it reads.
+    Sample(usize, usize),
+}
+
+impl Node {
+    pub fn dependencies(&self) -> Vec<usize> {
+        match self {
+            Node::Input => Vec::new(),
+            Node::Add(_, left, right) => vec![*left, *right],
+            Node::Optional(inner)
+            | Node::UnwrapOr(inner, _)
+            | Node::MapErr(inner, _)
+            | Node::Recover(inner, _)
+            | Node::Errors(inner)
+            | Node::OkOr(inner, _)
+            | Node::Filter(inner, _)
+            | Node::Map(inner, _)
+            | Node::GroupBy(inner, _)
+            | Node::Count(inner)
+            | Node::Sum(inner)
+            | Node::Window(inner, _)
+            | Node::Hold(inner)
+            | Node::Changes(inner) => vec![*inner],
+            Node::Sample(event, signal) => vec![*event, *signal],
+            Node::Switch(selector, target) => {
+                let mut res = vec![*selector];
+                res.extend(*target);
+                res
+            }
+        }
+    }
+
+    // The dependencies whose updates reach the node. Samples read their signal, but only run
+    // when their event occurs.
+    pub fn triggers(&self) -> Vec<usize> {
+        match self {
+            Node::Sample(event, _) => vec![*event],
+            node => node.dependencies(),
+        }
+    }
+
+    // Whether going from `old` to `new` is an update dependents and listeners should hear about.
+    pub fn fires(&self, old: &Wrapper, new: &Wrapper) -> bool {
+        !matches!(self, Node::Changes(_)) || old != new
+    }
+
+    pub fn evaluate(&self, id: usize, value: impl Fn(usize) -> Wrapper) -> Wrapper {
+        match self {
+            Node::Input => value(id),
+            Node::Add(overflow, left, right) => value(*left).add(&value(*right), *overflow),
+            Node::Optional(inner) | Node::Hold(inner) | Node::Changes(inner) => value(*inner),
+            Node::UnwrapOr(inner, default) => match value(*inner) {
+                Wrapper::Missing => default.clone(),
+                value => value,
+            },
+            Node::MapErr(inner, f) => match value(*inner) {
+                Wrapper::Err(error) => Wrapper::Err(Arc::new(f.call((*error).clone()))),
+                value => value,
+            },
+            Node::Recover(inner, f) => match value(*inner) {
+                Wrapper::Err(error) => f.call((*error).clone()),
+                value => value,
+            },
+            Node::Errors(inner) => match value(*inner) {
+                Wrapper::Err(error) => (*error).clone(),
+                _ => Wrapper::Missing,
+            },
+            Node::OkOr(inner, error) => match value(*inner) {
+                Wrapper::Missing => Wrapper::Err(Arc::new(error.clone())),
+                value => value,
+            },
+            Node::Switch(_, target) => target.map_or(Wrapper::Missing, value),
+            Node::Filter(inner, f) => filter(&value(id), &value(*inner), f),
+            Node::Map(inner, f) => map(&value(id), &value(*inner), f),
+            Node::GroupBy(inner, f) => group_by(&value(id), &value(*inner), f),
+            Node::Count(inner) => count(&value(id), &value(*inner)),
+            Node::Sum(inner) => sum(&value(id), &value(*inner)),
+            Node::Window(inner, buffer) => buffer.record(value(*inner), &value(id)),
+            Node::Sample(_, signal) => value(*signal),
+        }
+    }
+
+    fn is_collection(&self) -> bool {
+        matches!(
+            self,
+            Node::Filter(..) | Node::Map(..) | Node::GroupBy(..) | Node::Count(_) | Node::Sum(_)
+        )
+    }
+}
+
+fn changes(value: &Wrapper) -> impl Iterator<Item = &Delta> {
+    match value {
+        Wrapper::Map(collection) => collection.changes.iter(),
+        _ => [].iter(),
+    }
+}
+
+// Applies changes to the entries of a collection node, which has none until its first update.
+fn apply(own: &Wrapper, changes: impl IntoIterator<Item = (Wrapper, Option<Wrapper>)>) -> Wrapper {
+    let collection = match own {
+        Wrapper::Map(collection) => collection.apply(changes),
+        _ => Collection::default().apply(changes),
+    };
+    Wrapper::Map(Arc::new(collection))
+}
+
+fn u64_or_zero(value: &Wrapper) -> u64 {
+    match value {
+        Wrapper::U64(value) => *value,
+        _ => 0,
+    }
+}
+
+// Entries that stop passing are removed, and removing entries that never passed is a no-op.
+fn filter(own: &Wrapper, source: &Wrapper, f: &Predicate) -> Wrapper {
+    apply(
+        own,
+        changes(source).map(|change| match change {
+            Delta::Insert { key, value, .. } if f.test(value) => (key.clone(), Some(value.clone())),
+            Delta::Insert { key, .. } | Delta::Remove { key, .. } => (key.clone(), None),
+        }),
+    )
+}
+
+fn map(own: &Wrapper, source: &Wrapper, f: &Function) -> Wrapper {
+    apply(
+        own,
+        changes(source).map(|change| match change {
+            Delta::Insert { key, value, .. } => (key.clone(), Some(f.call(value.clone()))),
+            Delta::Remove { key, .. } => (key.clone(), None),
+        }),
+    )
+}
+
+// Entries whose value moves to another group leave their old group, which is removed once it
+// has no entries left.
+fn group_by(own: &Wrapper, source: &Wrapper, f: &Function) -> Wrapper {
+    let groups = match own {
+        Wrapper::Map(groups) => Arc::clone(groups),
+        _ => Arc::default(),
+    };
+    // Groups in the order they were first touched, so changes come out in a stable order.
+    let mut counts = Vec::<(Wrapper, u64)>::new();
+    let mut positions = FxHashMap::<Wrapper, usize>::default();
+    for change in changes(source) {
+        let (old, new) = match change {
+            Delta::Insert { value, old, .. } => (old.as_ref(), Some(value)),
+            Delta::Remove { old, .. } => (old.as_ref(), None),
+        };
+        let moves = old.map(|old| (old, false)).into_iter();
+        for (value, joins) in moves.chain(new.map(|new| (new, true))) {
+            let group = f.call(value.clone());
+            let pos = *positions.entry(group.clone()).or_insert_with(|| {
+                let count = groups.get(&group).map_or(0, |count| u64_or_zero(&count));
+                counts.push((group, count));
+                counts.len() - 1
+            });
+            let count = &mut counts[pos].1;
+            *count = if joins {
+                *count + 1
+            } else {
+                count.saturating_sub(1)
+            };
+        }
+    }
+    Wrapper::Map(Arc::new(groups.apply(counts.into_iter().map(
+        |(group, count)| (group, (count > 0).then_some(Wrapper::U64(count))),
+    ))))
+}
+
+fn count(own: &Wrapper, source: &Wrapper) -> Wrapper {
+    let mut count = u64_or_zero(own);
+    for change in changes(source) {
+        match change {
+            Delta::Insert { old: None, .. } => count += 1,
+            Delta::Remove { .. } => count = count.saturating_sub(1),
+            Delta::Insert { .. } => {}
+        }
+    }
+    Wrapper::U64(count)
+}
+
+fn sum(own: &Wrapper, source: &Wrapper) -> Wrapper {
+    let mut sum = u64_or_zero(own);
+    for change in changes(source) {
+        let (old, new) = match change {
+            Delta::Insert { value, old, .. } => (old.as_ref(), Some(value)),
+            Delta::Remove { old, .. } => (old.as_ref(), None),
+        };
+        sum = sum
+            .wrapping_sub(old.map_or(0, u64_or_zero))
+            .wrapping_add(new.map_or(0, u64_or_zero));
+    }
+    Wrapper::U64(sum)
+}
+
+#[derive(Clone, Copy)]
+pub enum Source<'a> {
+    Channel(&'a dyn Emitter),
+    // Values are handed to the engine directly by the thread that owns it.
+    Caller,
+    Disconnected,
+}
+
 pub struct Impl<'a> {
-    fields: Vec<Wrapper>,
-    listeners: Vec<Option<Box<dyn Listener>>>,
+    fields: Fields,
+    nodes: Vec<Node>,
+    descs: Vec<Apt>,
+    // Only the interpreter walks these, compiled tapes keep their own order.
+    dependents: Vec<Vec<usize>>,
+    heights: Vec<usize>,
+    // Every listener of each node. A node used to hold at most one, so listening to a signal twice
+    // silently replaced the first listener; now each is notified and dropped on its own.
+    listeners: Vec<Vec<Box<dyn Listener + Send>>>,
     signals: FxHashMap<Apt, usize>,
     inputs: FxHashMap<InputRef, usize>,
-    emitters: Vec<Option<&'a dyn Emitter>>,
+    emitters: Vec<Source<'a>>,
     emitters_to_fields: Vec<usize>,
-    prestart_queue: VecDeque<Update>,
+    prestart_queue: UpdateQueue,
+    pause_queue: UpdateQueue,
+    // Told which queue updates go to, so senders can refuse updates it would reject.
+    gate: Option<Arc<Gate>>,
+    observer: Arc<dyn EngineObserver>,
+    propagator: Propagator,
+    live_listeners: usize,
+    state: Lifecycle,
+    lifecycle: Arc<SharedLifecycle>,
+    drain_on_shutdown: bool,
+    // Every update propagated over the engine's lifetime, reported by a graceful shutdown.
+    processed: usize,
+    uninitialized: Uninitialized,
+    clock: Arc<dyn Clock>,
 }
 
 impl<'a> Impl<'a> {
-    pub fn new() -> Self {
+    pub fn new(
+        config: EngineConfig,
+        propagator: Propagator,
+        lifecycle: Arc<SharedLifecycle>,
+    ) -> Self {
         Self {
-            fields: Vec::default(),
+            fields: Fields::new(config.evaluator),
+            nodes: Vec::default(),
+            descs: Vec::default(),
+            dependents: Vec::default(),
+            heights: Vec::default(),
             listeners: Vec::default(),
             signals: FxHashMap::default(),
             inputs: FxHashMap::default(),
             emitters: Vec::default(),
             emitters_to_fields: Vec::default(),
-            prestart_queue: VecDeque::new(),
+            prestart_queue: UpdateQueue::new(QueueKind::Prestart, config.prestart_queue),
+            pause_queue: UpdateQueue::new(QueueKind::Pause, config.pause_queue),
+            gate: None,
+            observer: config.observer,
+            propagator,
+            live_listeners: 0,
+            state: Lifecycle::Created,
+            lifecycle,
+            drain_on_shutdown: false,
+            processed: 0,
+            uninitialized: config.uninitialized,
+            clock: config.clock,
+        }
+    }
+
+    pub fn restore(
+        snapshot: Snapshot,
+        config: EngineConfig,
+        propagator: Propagator,
+        lifecycle: Arc<SharedLifecycle>,
+    ) -> Self {
+        let mut res = Self::new(config, propagator, lifecycle);
+        // Dependencies come first, so registering a node never recurses.
+        for SnapshotNode {
+            signal,
+            value,
+            history,
+        } in snapshot.nodes
+        {
+            let id = res.get_signal_id(signal);
+            if let Node::Window(_, buffer) = &res.nodes[id] {
+                buffer.restore(history);
+            }
+            res.fields.set(id, value);
+        }
+        res.rewire_switches();
+        res
+    }
+
+    // Shards share their gate, where their sharded engine queues updates for all of them.
+    pub fn guard_queues(mut self, gate: Arc<Gate>) -> Self {
+        self.gate = Some(gate);
+        self.guard();
+        self
+    }
+
+    fn guard(&self) {
+        if let Some(gate) = &self.gate {
+            let queue = match self.state {
+                Lifecycle::Created => Some(&self.prestart_queue),
+                Lifecycle::Paused => Some(&self.pause_queue),
+                Lifecycle::Running | Lifecycle::ShuttingDown | Lifecycle::Stopped => None,
+            };
+            gate.guard(queue, &self.observer);
         }
     }
 
-    pub fn run_engine(mut self, receiver: &Receiver<Command>, arena: &'a Arena<Box<dyn Emitter>>) {
+    pub fn run_engine(
+        mut self,
+        receiver: &'a Receiver<Command>,
+        arena: &'a Arena<Box<dyn Emitter>>,
+    ) -> usize {
         let mut select = Select::new();
         select.recv(receiver);
 
-        let unparker_or_shutdown: Option<Unparker> = loop {
+        while self.state != Lifecycle::ShuttingDown {
             let op = select.select();
             if op.index() == 0 {
-                let command = op.recv(receiver);
-                match command {
-                    Ok(Command::Start(unparker)) => {
-                        break Some(unparker);
-                    }
-                    Ok(Command::Shutdown) => {
-                        break None;
-                    }
-                    Ok(Command::Listen {
-                        signal,
-                        listener,
-                        unparker,
-                    }) => {
-                        self.add_listener(signal, listener);
-                        unparker.unpark();
-                    }
-                    Ok(Command::Emit {
-                        input,
-                        rtype,
-                        emitter,
-                        unparker,
-                    }) => {
-                        let ptr = arena.alloc(emitter);
-                        self.emitters.push(Some(&**ptr));
-                        ptr.install(&mut select);
-                        let field = self.get_signal_id(Arc::new(
-                            Typed {
-                                desc: Input(input),
-                                rtype,
-                            }
-                            .into(),
-                        ));
-                        self.inputs.insert(input, field);
-                        self.emitters_to_fields.push(field);
-                        unparker.unpark();
+                match op.recv(receiver) {
+                    Ok(command) => self.dispatch(command, &mut select, arena),
+                    Err(_) => {
+                        self.transition(Lifecycle::ShuttingDown);
                     }
-                    Err(_) => break None,
                 }
             } else {
                 let index = op.index();
                 if let Ok(update) = self.create_update(op) {
-                    self.prestart_queue.push_back(update);
+                    self.accept_update(update);
                 } else {
                     select.remove(index);
-                    self.emitters[index - 1] = None;
+                    self.disconnect_emitter(index - 1);
                 }
             }
-        };
-        if let Some(unparker) = unparker_or_shutdown {
-            self.drain_queue();
-            unparker.unpark();
-            self.work(&mut select, receiver, arena);
         }
+        self.finish()
     }
-    fn work(
-        mut self,
-        select: &'a mut Select<'a>,
-        receiver: &'a Receiver<Command>,
+
+    pub fn finish(&mut self) -> usize {
+        if self.drain_on_shutdown {
+            self.drain();
+        }
+        self.listeners.clear();
+        self.transition(Lifecycle::Stopped);
+        self.processed
+    }
+
+    pub fn state(&self) -> Lifecycle {
+        self.state
+    }
+
+    pub fn start(&mut self) {
+        if self.transition(Lifecycle::Running) {
+            self.drain_prestart_queue();
+        }
+    }
+
+    pub fn pause(&mut self) {
+        self.transition(Lifecycle::Paused);
+    }
+
+    pub fn resume(&mut self) {
+        if self.transition(Lifecycle::Running) {
+            self.drain_pause_queue();
+        }
+    }
+
+    pub fn shutdown(&mut self, drain: bool) {
+        self.drain_on_shutdown = drain;
+        self.transition(Lifecycle::ShuttingDown);
+    }
+
+    fn dispatch(
+        &mut self,
+        command: Command,
+        select: &mut Select<'a>,
         arena: &'a Arena<Box<dyn Emitter>>,
     ) {
-        loop {
-            let op = select.select();
-            if op.index() == 0 {
-                match op.recv(receiver) {
-                    Ok(Command::Start(unparker)) => {
-                        unparker.unpark();
-                        eprintln!("Engine start called on running engine!");
-                    }
-                    Ok(Command::Shutdown) => {
-                        break;
-                    }
-                    Ok(Command::Listen {
-                        signal,
-                        listener,
-                        unparker,
-                    }) => {
-                        self.add_listener(signal, listener);
-                        unparker.unpark();
-                    }
-                    Ok(Command::Emit {
-                        input,
-                        rtype,
-                        emitter,
-                        unparker,
-                    }) => {
-                        let ptr = arena.alloc(emitter);
-                        self.emitters.push(Some(&**ptr));
-                        ptr.install(select);
-                        let field = self.get_signal_id(Arc::new(
-                            Typed {
-                                desc: Input(input),
-                                rtype,
-                            }
-                            .into(),
-                        ));
-                        self.inputs.insert(input, field);
-                        self.emitters_to_fields.push(field);
-                        unparker.unpark();
-                    }
-                    Err(_) => break,
-                }
-            } else {
-                let index = op.index();
-                if let Ok(update) = self.create_update(op) {
-                    self.update(update);
-                } else {
-                    select.remove(index);
-                    self.emitters[index - 1] = None;
+        self.observer.command_received(command.kind());
+        match command {
+            Command::Start(unparker) => {
+                self.start();
+                unparker.unpark();
+            }
+            Command::Pause(unparker) => {
+                self.pause();
+                unparker.unpark();
+            }
+            Command::Resume(unparker) => {
+                self.resume();
+                unparker.unpark();
+            }
+            Command::Shutdown { drain } => self.shutdown(drain),
+            Command::Listen {
+                signal,
+                listener,
+                options,
+                unparker,
+            } => {
+                self.add_listener(signal, listener, options);
+                unparker.unpark();
+            }
+            Command::Emit {
+                input,
+                rtype,
+                emitter,
+                unparker,
+            } => {
+                let field = self.input_field(input, rtype);
+                self.install_emitter(field, emitter, select, arena);
+                unparker.unpark();
+            }
+            Command::Describe(reply) => {
+                let _ = reply.send(self.describe());
+            }
+            Command::Snapshot(reply) => {
+                let _ = reply.send(self.snapshot());
+            }
+            Command::Detach(reply) => {
+                self.transition(Lifecycle::ShuttingDown);
+                let _ = reply.send(self.detach());
+            }
+            Command::Absorb { detached, unparker } => {
+                self.absorb(detached, select, arena);
+                unparker.unpark();
+            }
+        }
+    }
+
+    fn install_emitter(
+        &mut self,
+        field: usize,
+        emitter: Box<dyn Emitter + Send>,
+        select: &mut Select<'a>,
+        arena: &'a Arena<Box<dyn Emitter>>,
+    ) {
+        let ptr: &'a dyn Emitter = &**arena.alloc(emitter);
+        ptr.install(select);
+        self.register_emitter(field, Source::Channel(ptr));
+    }
+
+    pub fn register_emitter(&mut self, field: usize, source: Source<'a>) -> usize {
+        self.emitters.push(source);
+        self.emitters_to_fields.push(field);
+        self.emitters.len() - 1
+    }
+
+    pub fn input_field(&mut self, input: InputRef, rtype: Type) -> usize {
+        self.get_signal_id(Arc::new(
+            Typed {
+                desc: Input(input),
+                rtype,
+            }
+            .into(),
+        ))
+    }
+
+    // Hands the whole graph over to another engine thread. Pending emitter messages stay in their
+    // channels, and the receiving thread picks them up from where this one stopped.
+    fn detach(&mut self) -> Detached {
+        let mut queued = Vec::new();
+        while let Some(update) = self.prestart_queue.pop() {
+            queued.push(update);
+        }
+        while let Some(update) = self.pause_queue.pop() {
+            queued.push(update);
+        }
+        let emitters = self
+            .emitters
+            .iter()
+            .zip(&self.emitters_to_fields)
+            .filter_map(|(emitter, field)| match emitter {
+                Source::Channel(emitter) => Some((*field, emitter.detach())),
+                Source::Caller | Source::Disconnected => None,
+            })
+            .collect();
+        let fields = &self.fields;
+        let nodes = self
+            .descs
+            .iter()
+            .zip(&self.nodes)
+            .zip(self.listeners.iter_mut())
+            .enumerate()
+            .map(|(id, ((signal, node), listeners))| DetachedNode {
+                signal: Arc::clone(signal),
+                value: fields.get(id),
+                node: node.clone(),
+                listeners: std::mem::take(listeners),
+            })
+            .collect();
+        self.live_listeners = 0;
+        self.observer.live_listeners(0);
+        Detached {
+            nodes,
+            emitters,
+            queued,
+            processed: self.processed,
+        }
+    }
+
+    fn absorb(
+        &mut self,
+        detached: Detached,
+        select: &mut Select<'a>,
+        arena: &'a Arena<Box<dyn Emitter>>,
+    ) {
+        let mut ids = Vec::with_capacity(detached.nodes.len());
+        for DetachedNode {
+            signal,
+            value,
+            node,
+            listeners,
+        } in detached.nodes
+        {
+            let id = self.get_signal_id(signal);
+            if let (Node::Window(_, buffer), Node::Window(_, history)) = (&self.nodes[id], node) {
+                buffer.adopt(&history);
+            }
+            self.register_signals(&value);
+            self.fields.set(id, value);
+            self.live_listeners += listeners.len();
+            self.listeners[id].extend(listeners);
+            ids.push(id);
+        }
+        self.rewire_switches();
+        self.observer.live_listeners(self.live_listeners);
+        self.processed += detached.processed;
+        for (node, emitter) in detached.emitters {
+            self.install_emitter(ids[node], emitter, select, arena);
+        }
+        for Update { input_pos, value } in detached.queued {
+            self.accept_update(Update {
+                input_pos: ids[input_pos],
+                value,
+            });
+        }
+    }
+
+    // Returns whether the engine moved to `to`. Handles validate the transitions they request,
+    // but sharded engines also detach and shut down shards on their own, so a rejected transition
+    // is reported to the observer and leaves the engine where it was.
+    fn transition(&mut self, to: Lifecycle) -> bool {
+        match self.state.transition(to) {
+            Ok(state) => {
+                self.observer.lifecycle_changed(self.state, state);
+                self.state = state;
+                self.guard();
+                if to == Lifecycle::Stopped {
+                    let _ = self.lifecycle.transition(Lifecycle::Stopped);
                 }
+                true
+            }
+            Err(error) => {
+                self.observer.lifecycle_rejected(error);
+                false
             }
         }
     }
 
-    fn drain_queue(&mut self) {
-        while let Some(update) = self.prestart_queue.pop_front() {
+    // Whether an update of the input would be queued or propagated, reporting it to the observer
+    // when the queue it would go to rejects it.
+    pub fn admits(&self, input_pos: usize) -> bool {
+        let queue = match self.state {
+            Lifecycle::Created => &self.prestart_queue,
+            Lifecycle::Paused => &self.pause_queue,
+            Lifecycle::Running | Lifecycle::ShuttingDown | Lifecycle::Stopped => return true,
+        };
+        let Some(overflow) = queue.rejects(input_pos) else {
+            return true;
+        };
+        self.observer.update_dropped(overflow.queue);
+        self.observer.queue_overflowed(overflow);
+        false
+    }
+
+    pub fn accept_update(&mut self, update: Update) {
+        match self.state {
+            Lifecycle::Created => self.prestart_queue.enqueue(&*self.observer, update),
+            Lifecycle::Paused => self.pause_queue.enqueue(&*self.observer, update),
+            Lifecycle::Running => self.update(update),
+            Lifecycle::ShuttingDown | Lifecycle::Stopped => {}
+        }
+    }
+
+    fn drain_prestart_queue(&mut self) {
+        while let Some(update) = self.prestart_queue.pop() {
             self.update(update);
         }
+        self.observer.queue_depth(QueueKind::Prestart, 0);
+    }
+
+    fn drain_pause_queue(&mut self) {
+        while let Some(update) = self.pause_queue.pop() {
+            self.update(update);
+        }
+        self.observer.queue_depth(QueueKind::Pause, 0);
+    }
+
+    // Propagates everything that was sent before shutdown, including updates held back by the
+    // prestart and pause queues. Channels are measured up front, so senders that keep going
+    // cannot hold the engine open.
+    fn drain(&mut self) {
+        while let Some(update) = self.prestart_queue.pop() {
+            self.update(update);
+        }
+        while let Some(update) = self.pause_queue.pop() {
+            self.update(update);
+        }
+        let pending = self
+            .emitters
+            .iter()
+            .map(|emitter| match emitter {
+                Source::Channel(emitter) => emitter.pending(),
+                Source::Caller | Source::Disconnected => 0,
+            })
+            .collect::<Vec<_>>();
+        for (id, pending) in pending.into_iter().enumerate() {
+            for _ in 0..pending {
+                let Source::Channel(emitter) = self.emitters[id] else {
+                    break;
+                };
+                match emitter.try_receive() {
+                    Ok(value) => {
+                        let input_pos = self.emitters_to_fields[id];
+                        self.update(Update { input_pos, value });
+                    }
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => self.disconnect_emitter(id),
+                }
+            }
+        }
+    }
+
+    pub fn disconnect_emitter(&mut self, id: usize) {
+        self.emitters[id] = Source::Disconnected;
+        if let Input(input) = self.descs[self.emitters_to_fields[id]].desc {
+            self.observer.emitter_disconnected(input);
+        }
     }
 
     fn create_update(&mut self, op: SelectedOperation) -> RecvResult<Update> {
         let id = op.index() - 1;
-        let value = self.emitters[id]
-            .expect("Emitter already discarded")
-            .receive(op)?;
+        let Source::Channel(emitter) = self.emitters[id] else {
+            unreachable!("Only channel emitters are selected")
+        };
+        let value = emitter.receive(op)?;
         let input_pos = self.emitters_to_fields[id];
         Ok(Update { input_pos, value })
     }
 
-    fn update(&mut self, Update { input_pos, value }: Update) {
-        self.fields[input_pos] = value.clone();
-        let needs_removal = if let Some(callback) = self.listeners[input_pos].as_ref() {
-            !matches!(callback.accept(value), Ok(()))
-        } else {
-            false
+    pub fn update(&mut self, Update { input_pos, value }: Update) {
+        let started = Instant::now();
+        self.processed += 1;
+        let value = self.fields.get(input_pos).apply(value);
+        self.register_signals(&value);
+        let listeners = &mut self.listeners;
+        let observer = &*self.observer;
+        let mut removed = 0;
+        let mut failed = Vec::new();
+        let mut notify = |id: usize, value: &Wrapper| {
+            if let Wrapper::Err(_) = value {
+                failed.push(id);
+            }
+            let before = listeners[id].len();
+            listeners[id].retain(|callback| callback.accept(value).is_ok());
+            for _ in listeners[id].len()..before {
+                observer.listener_removed(id);
+                removed += 1;
+            }
         };
-        if needs_removal {
-            self.listeners[input_pos] = None;
+        let touched = match &mut self.fields {
+            Fields::Compiled(tape) => {
+                tape.set(input_pos, &value);
+                let signals = &self.signals;
+                let touched = tape.run(
+                    input_pos,
+                    |value| selected(signals, value),
+                    |id, value| notify(id, &value),
+                );
+                for (id, target) in tape.rewired() {
+                    Self::retarget(&mut self.nodes[id], target);
+                }
+                touched
+            }
+            Fields::Boxed(fields) => {
+                fields[input_pos] = value;
+                let graph = Graph {
+                    fields,
+                    nodes: &mut self.nodes,
+                    dependents: &mut self.dependents,
+                    heights: &mut self.heights,
+                    signals: &self.signals,
+                };
+                self.propagator.propagate(graph, input_pos, &mut notify)
+            }
+        };
+        for id in failed {
+            self.report_overflow(id);
+        }
+        if removed > 0 {
+            self.live_listeners -= removed;
+            self.observer.live_listeners(self.live_listeners);
         }
+        self.observer.update_propagated(Propagation {
+            input: input_pos,
+            compiled: matches!(self.fields, Fields::Compiled(_)),
+            touched,
+            latency: started.elapsed(),
+        });
     }
 
-    fn add_listener(&mut self, signal: Apt, listener: Box<dyn Listener>) {
+    pub fn add_listener(
+        &mut self,
+        signal: Apt,
+        listener: Box<dyn Listener + Send>,
+        options: ListenOptions,
+    ) {
         let id = self.get_signal_id(signal);
-        self.listeners[id] = Some(listener);
+        if options.replay_current && listener.accept(&self.fields.get(id).replayed()).is_err() {
+            return;
+        }
+        self.listeners[id].push(listener);
+        self.live_listeners += 1;
+        self.observer.live_listeners(self.live_listeners);
     }
 
-    fn get_signal_id(&mut self, signal: Apt) -> usize {
-        if let Some(id) = self.signals.get(&signal) {
-            *id
-        } else {
-            let Typed { desc, rtype } = &**signal;
-            let res = match desc {
-                Input(input) => {
-                    self.fields.push(Wrapper::zeroed(*rtype));
-                    self.listeners.push(None);
-                    let res = self.fields.len() - 1;
-                    self.inputs.insert(*input, res);
-                    res
+    pub fn describe(&self) -> GraphDescription {
+        let mut emitters = vec![0; self.fields.len()];
+        for (emitter, field) in self.emitters.iter().zip(&self.emitters_to_fields) {
+            if !matches!(emitter, Source::Disconnected) {
+                emitters[*field] += 1;
+            }
+        }
+        let nodes = self
+            .descs
+            .iter()
+            .enumerate()
+            .map(|(id, signal)| NodeDescription {
+                id,
+                kind: (&signal.desc).into(),
+                rtype: signal.rtype,
+                dependencies: self.nodes[id].dependencies(),
+                listeners: self.listeners[id].len(),
+                emitters: emitters[id],
+                value: self.fields.get(id),
+            })
+            .collect();
+        GraphDescription { nodes }
+    }
+
+    pub fn snapshot(&self) -> Result<Vec<u8>, SnapshotError> {
+        let nodes = self
+            .descs
+            .iter()
+            .zip(&self.nodes)
+            .enumerate()
+            .map(|(id, (signal, node))| SnapshotNode {
+                signal: Arc::clone(signal),
+                value: self.fields.get(id),
+                history: match node {
+                    Node::Window(_, buffer) => buffer.history(),
+                    _ => WindowHistory::default(),
+                },
+            })
+            .collect();
+        Snapshot { nodes }.encode()
+    }
+
+    // Checked nodes fail with the overflow as their error.
+    fn report_overflow(&self, id: usize) {
+        let Node::Add(Overflow::Checked, ..) = self.nodes[id] else {
+            return;
+        };
+        if let Wrapper::Err(error) = self.fields.get(id) {
+            if let Wrapper::Overflow(overflow) = *error {
+                self.observer.arithmetic_overflowed(id, overflow);
+            }
+        }
+    }
+
+    // Signals handed to the engine as values are registered up front, so switches can find
+    // them while propagating.
+    fn register_signals(&mut self, value: &Wrapper) {
+        match value {
+            Wrapper::Signal(signal) => {
+                self.get_signal_id(Arc::clone(signal));
+            }
+            Wrapper::Err(error) => self.register_signals(error),
+            _ => {}
+        }
+    }
+
+    // Points every switch at its selector's current signal, after nodes were added with values
+    // that did not come through propagation.
+    fn rewire_switches(&mut self) {
+        match &mut self.fields {
+            Fields::Compiled(tape) => {
+                let signals = &self.signals;
+                tape.rewire_all(|value| selected(signals, value));
+                for (id, target) in tape.rewired() {
+                    Self::retarget(&mut self.nodes[id], target);
                 }
-                Add(left, right) => {
-                    let left_id = self.get_signal_id(left.clone());
-                    let right_id = self.get_signal_id(right.clone());
-                    let left = &self.fields[left_id];
-                    let right = &self.fields[right_id];
-
-                    let new = left.add(right);
-                    self.fields.push(new);
-                    self.listeners.push(None);
-                    let res = self.fields.len() - 1;
-
-                    res
+            }
+            Fields::Boxed(fields) => {
+                let mut graph = Graph {
+                    fields,
+                    nodes: &mut self.nodes,
+                    dependents: &mut self.dependents,
+                    heights: &mut self.heights,
+                    signals: &self.signals,
+                };
+                for id in 0..graph.nodes.len() {
+                    graph.rewire(id, &mut BTreeSet::new());
                 }
-            };
-            self.signals.insert(signal.clone(), res);
-            res
+            }
+        }
+    }
+
+    // Tapes keep their own order, so rewiring a compiled switch only updates its node.
+    fn retarget(node: &mut Node, target: Option<usize>) {
+        if let Node::Switch(selector, _) = *node {
+            *node = Node::Switch(selector, target);
+        }
+    }
+
+    fn push_field(&mut self, signal: Apt, node: Node, value: Wrapper) -> usize {
+        let id = self.fields.len();
+        let mut height = 0;
+        for dependency in node.dependencies() {
+            height = height.max(self.heights[dependency] + 1);
+        }
+        for trigger in node.triggers() {
+            self.dependents[trigger].push(id);
+        }
+        self.heights.push(height);
+        self.fields.push(&node, signal.rtype, value);
+        self.nodes.push(node);
+        self.descs.push(Arc::clone(&signal));
+        self.dependents.push(Vec::new());
+        self.listeners.push(Vec::new());
+        self.signals.insert(signal, id);
+        id
+    }
+
+    // Registers a derived node with its value computed from the current values of its
+    // dependencies.
+    fn push_node(&mut self, signal: Apt, node: Node) -> usize {
+        let id = self.fields.len();
+        let value = if let Node::Window(_, buffer) = &node {
+            buffer.empty()
+        } else if node.is_collection() {
+            // Collection nodes start out empty and see everything their source holds as new.
+            let own = Wrapper::zeroed(signal.rtype);
+            node.evaluate(id, |dependency| {
+                if dependency == id {
+                    own.clone()
+                } else {
+                    self.fields.get(dependency).replayed()
+                }
+            })
+        } else {
+            node.evaluate(id, |id| self.fields.get(id))
+        };
+        let id = self.push_field(signal, node, value);
+        self.report_overflow(id);
+        id
+    }
+
+    fn get_signal_id(&mut self, signal: Apt) -> usize {
+        if let Some(id) = self.signals.get(&signal) {
+            return *id;
+        }
+        let Typed { desc, rtype } = &**signal;
+        match desc {
+            Input(input) => {
+                let input = *input;
+                // Maps are never missing, they start out empty.
+                let value = match (self.uninitialized, rtype) {
+                    (Uninitialized::Zeroed, _) | (_, Type::Map(..)) => Wrapper::zeroed(*rtype),
+                    (Uninitialized::Missing, _) => Wrapper::Missing,
+                };
+                let res = self.push_field(signal.clone(), Node::Input, value);
+                self.inputs.insert(input, res);
+                res
+            }
+            Hold(event, init) => {
+                let event_id = self.get_signal_id(event.clone());
+                self.register_signals(init);
+                // Until the event occurs, which it has not since the node exists.
+                self.push_field(signal.clone(), Node::Hold(event_id), init.clone())
+            }
+            desc => {
+                let node = self.derived_node(desc);
+                self.push_node(signal.clone(), node)
+            }
+        }
+    }
+
+    // Registers the operands of a derived node, and builds the node on top of them.
+    fn derived_node(&mut self, desc: &Desc) -> Node {
+        match desc {
+            Add(left, right) | SaturatingAdd(left, right) | CheckedAdd(left, right) => {
+                let overflow = match desc {
+                    SaturatingAdd(..) => Overflow::Saturating,
+                    CheckedAdd(..) => Overflow::Checked,
+                    _ => Overflow::Wrapping,
+                };
+                let left_id = self.get_signal_id(left.clone());
+                let right_id = self.get_signal_id(right.clone());
+                Node::Add(overflow, left_id, right_id)
+            }
+            Optional(inner) => Node::Optional(self.get_signal_id(inner.clone())),
+            UnwrapOr(inner, default) => {
+                let inner_id = self.get_signal_id(inner.clone());
+                self.register_signals(default);
+                Node::UnwrapOr(inner_id, default.clone())
+            }
+            MapErr(inner, f) => Node::MapErr(self.get_signal_id(inner.clone()), f.clone()),
+            Recover(inner, f) => Node::Recover(self.get_signal_id(inner.clone()), f.clone()),
+            Errors(inner) => Node::Errors(self.get_signal_id(inner.clone())),
+            OkOr(inner, error) => {
+                let inner_id = self.get_signal_id(inner.clone());
+                self.register_signals(error);
+                Node::OkOr(inner_id, error.clone())
+            }
+            Switch(selector) => {
+                let selector_id = self.get_signal_id(selector.clone());
+                let target = selected(&self.signals, &self.fields.get(selector_id));
+                Node::Switch(selector_id, target)
+            }
+            Filter(inner, f) => Node::Filter(self.get_signal_id(inner.clone()), f.clone()),
+            Map(inner, f) => Node::Map(self.get_signal_id(inner.clone()), f.clone()),
+            GroupBy(inner, f) => Node::GroupBy(self.get_signal_id(inner.clone()), f.clone()),
+            Count(inner) => Node::Count(self.get_signal_id(inner.clone())),
+            Sum(inner) => Node::Sum(self.get_signal_id(inner.clone())),
+            Window(inner, aggregate, window) => {
+                let inner_id = self.get_signal_id(inner.clone());
+                let buffer = Buffer::timed(*aggregate, *window, Arc::clone(&self.clock));
+                Node::Window(inner_id, Arc::new(buffer))
+            }
+            LastN(inner, n) => {
+                let inner_id = self.get_signal_id(inner.clone());
+                let buffer = Buffer::last(*n, Arc::clone(&self.clock));
+                Node::Window(inner_id, Arc::new(buffer))
+            }
+            Changes(inner) => Node::Changes(self.get_signal_id(inner.clone())),
+            Sample(event, inner) => {
+                let event_id = self.get_signal_id(event.clone());
+                let inner_id = self.get_signal_id(inner.clone());
+                Node::Sample(event_id, inner_id)
+            }
+            Input(_) | Hold(..) => unreachable!("{desc} is not derived from its operands"),
         }
     }
 }
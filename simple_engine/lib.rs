@@ -1,66 +1,171 @@
 use std::thread::{self, JoinHandle};
 
-use commands::Command;
+use commands::{Command, Detached};
+pub use config::{EngineConfig, Uninitialized};
 use crossbeam_channel::{Receiver, Sender};
+use crossbeam_utils::sync::Unparker;
 use engine_base::{
+    describe::GraphDescription,
     hash::Prehashed,
+    lifecycle::{Lifecycle, LifecycleError, SharedLifecycle},
     operators::{types::RType, InputRef, Signal, Typed},
-    waiting::{MaybeWaiting, ParkWaiting, ThreadJoinWaiting, Waiting},
-    Engine,
+    snapshot::{Snapshot, SnapshotError},
+    waiting::{MapWaiting, MaybeWaiting, ParkWaiting, ReplyWaiting, ThreadJoinWaiting, Waiting},
+    Engine, ListenOptions,
 };
 use internal::Impl;
+pub use parallel::ParallelEngine;
+use propagate::Propagator;
+pub use queue::{Coalesce, Overflow, QueueConfig};
+pub use sharded::ShardedEngine;
 use std::sync::Arc;
-use transport::{Emitter, EmitterImpl, ListenerImpl};
+pub use synchronous::{SyncEngine, SyncSender};
+pub use tape::Evaluator;
+pub use transport::EngineSender;
+use transport::{Emitter, EmitterImpl, Gate, ListenerImpl};
 use typed_arena::Arena;
 
 mod commands;
+mod config;
 mod internal;
+mod parallel;
+mod propagate;
+mod queue;
+mod sharded;
+mod synchronous;
+mod tape;
 mod transport;
+mod window;
 
 pub(crate) type Apt = Arc<Prehashed<Typed>>;
 
 pub struct SimpleEngine {
     sender: Sender<Command>,
-    handle: JoinHandle<()>,
+    handle: JoinHandle<usize>,
+    lifecycle: Arc<SharedLifecycle>,
+    gate: Arc<Gate>,
 }
 
 impl SimpleEngine {
     pub fn new() -> Self {
+        Self::with_config(EngineConfig::default())
+    }
+
+    pub fn with_config(config: EngineConfig) -> Self {
+        Self::with_propagator(config, || Propagator::Sequential)
+    }
+
+    /// # Errors
+    ///
+    /// Fails like `Engine::restore`.
+    pub fn restore_with_config(bytes: &[u8], config: EngineConfig) -> Result<Self, SnapshotError> {
+        Self::restore_with_propagator(bytes, config, || Propagator::Sequential)
+    }
+
+    pub(crate) fn with_propagator(
+        config: EngineConfig,
+        propagator: impl FnOnce() -> Propagator + Send + 'static,
+    ) -> Self {
+        let gate = Gate::new();
+        let guarded = Arc::clone(&gate);
+        Self::spawn(gate, move |lifecycle| {
+            Impl::new(config, propagator(), lifecycle).guard_queues(guarded)
+        })
+    }
+
+    // Shards of one engine share its gate, since their emitters move between them.
+    pub(crate) fn shard(config: EngineConfig, gate: Arc<Gate>) -> Self {
+        Self::spawn(gate, move |lifecycle| {
+            Impl::new(config, Propagator::Sequential, lifecycle)
+        })
+    }
+
+    pub(crate) fn restore_with_propagator(
+        bytes: &[u8],
+        config: EngineConfig,
+        propagator: impl FnOnce() -> Propagator + Send + 'static,
+    ) -> Result<Self, SnapshotError> {
+        let snapshot = Snapshot::decode(bytes)?;
+        let gate = Gate::new();
+        let guarded = Arc::clone(&gate);
+        Ok(Self::spawn(gate, move |lifecycle| {
+            Impl::restore(snapshot, config, propagator(), lifecycle).guard_queues(guarded)
+        }))
+    }
+
+    pub(crate) fn from_snapshot(
+        snapshot: Snapshot,
+        config: EngineConfig,
+        propagator: impl FnOnce() -> Propagator + Send + 'static,
+        gate: Arc<Gate>,
+    ) -> Self {
+        Self::spawn(gate, move |lifecycle| {
+            Impl::restore(snapshot, config, propagator(), lifecycle)
+        })
+    }
+
+    fn spawn(
+        gate: Arc<Gate>,
+        internal: impl FnOnce(Arc<SharedLifecycle>) -> Impl<'static> + Send + 'static,
+    ) -> Self {
         let (sender, receiver) = crossbeam_channel::unbounded();
+        let lifecycle = Arc::new(SharedLifecycle::new());
+        let thread_lifecycle = Arc::clone(&lifecycle);
         let handle = thread::spawn(move || {
-            let internal = Impl::new();
+            let internal = internal(thread_lifecycle);
             let arena = Arena::<Box<dyn Emitter>>::new();
-            internal.run_engine(&receiver, &arena);
+            internal.run_engine(&receiver, &arena)
         });
-        Self { sender, handle }
+        Self {
+            sender,
+            handle,
+            lifecycle,
+            gate,
+        }
     }
-}
 
-impl Engine for SimpleEngine {
-    fn start(&self) -> impl MaybeWaiting<()> {
-        let (wait, unparker) = ParkWaiting::create(());
-        self.sender
-            .send(Command::Start(unparker))
-            .expect("Engine thread is dead");
+    pub(crate) fn transition(
+        &self,
+        from: Lifecycle,
+        to: Lifecycle,
+        command: impl FnOnce(Unparker) -> Command,
+    ) -> ParkWaiting<Result<(), LifecycleError>> {
+        let result = self.lifecycle.transition_from(from, to);
+        let (wait, unparker) = ParkWaiting::create(result);
+        if result.is_ok() {
+            self.sender
+                .send(command(unparker))
+                .expect("Engine thread is dead");
+        } else {
+            unparker.unpark();
+        }
         wait
     }
 
-    fn listen<T: RType>(&self, signal: Signal<T>) -> impl MaybeWaiting<Receiver<T>> {
+    // The trait methods capture the borrow of `self` in their return types, so wrappers that
+    // hold their shards behind a lock use these instead.
+    pub(crate) fn add_listener<T: RType>(
+        &self,
+        signal: Signal<T>,
+        options: ListenOptions,
+    ) -> ParkWaiting<Receiver<T>> {
         let (s, r) = crossbeam_channel::unbounded();
         let (wait, unparker) = ParkWaiting::create(r);
         self.sender
             .send(Command::Listen {
                 signal: signal.get_desc(),
                 listener: Box::new(ListenerImpl::new(s)),
+                options,
                 unparker,
             })
             .expect("Engine thread is dead");
         wait
     }
 
-    fn emit<T: RType>(&self, input: InputRef) -> impl MaybeWaiting<Sender<T>> {
+    pub(crate) fn add_emitter<T: RType>(&self, input: InputRef) -> ParkWaiting<EngineSender<T>> {
         let (s, r) = crossbeam_channel::unbounded();
-        let (wait, unparker) = ParkWaiting::create(s);
+        let sender = EngineSender::new(s, Arc::clone(&self.gate), input);
+        let (wait, unparker) = ParkWaiting::create(sender);
         self.sender
             .send(Command::Emit {
                 input,
@@ -72,15 +177,89 @@ impl Engine for SimpleEngine {
         wait
     }
 
-    fn shutdown(self) -> impl Waiting<()> {
+    pub(crate) fn request<T>(&self, command: impl FnOnce(Sender<T>) -> Command) -> ReplyWaiting<T> {
+        let (s, r) = crossbeam_channel::bounded(1);
+        self.sender.send(command(s)).expect("Engine thread is dead");
+        ReplyWaiting::from(r)
+    }
+
+    pub(crate) fn detach(self) -> Detached {
+        let detached = self.request(Command::Detach).wait();
+        ThreadJoinWaiting::from(self.handle).wait();
+        detached
+    }
+
+    pub(crate) fn absorb(&self, detached: Detached) -> ParkWaiting<()> {
+        let (wait, unparker) = ParkWaiting::create(());
+        self.sender
+            .send(Command::Absorb { detached, unparker })
+            .expect("Engine thread is dead");
+        wait
+    }
+
+    pub(crate) fn stop(self, drain: bool) -> ThreadJoinWaiting<usize> {
+        let _ = self.lifecycle.transition(Lifecycle::ShuttingDown);
+        self.gate.close();
         self.sender
-            .send(Command::Shutdown)
+            .send(Command::Shutdown { drain })
             .expect("Engine thread is dead");
 
         ThreadJoinWaiting::from(self.handle)
     }
 }
 
+impl Engine for SimpleEngine {
+    type Sender<T: RType> = EngineSender<T>;
+
+    fn lifecycle(&self) -> Lifecycle {
+        self.lifecycle.get()
+    }
+
+    fn start(&self) -> impl MaybeWaiting<Result<(), LifecycleError>> {
+        self.transition(Lifecycle::Created, Lifecycle::Running, Command::Start)
+    }
+
+    fn pause(&self) -> impl MaybeWaiting<Result<(), LifecycleError>> {
+        self.transition(Lifecycle::Running, Lifecycle::Paused, Command::Pause)
+    }
+
+    fn resume(&self) -> impl MaybeWaiting<Result<(), LifecycleError>> {
+        self.transition(Lifecycle::Paused, Lifecycle::Running, Command::Resume)
+    }
+
+    fn listen_with<T: RType>(
+        &self,
+        signal: Signal<T>,
+        options: ListenOptions,
+    ) -> impl MaybeWaiting<Receiver<T>> {
+        self.add_listener(signal, options)
+    }
+
+    fn emit<T: RType>(&self, input: InputRef) -> impl MaybeWaiting<EngineSender<T>> {
+        self.add_emitter(input)
+    }
+
+    fn describe(&self) -> impl Waiting<GraphDescription> {
+        self.request(Command::Describe)
+    }
+
+    fn snapshot(&self) -> impl Waiting<Result<Vec<u8>, SnapshotError>> {
+        self.request(Command::Snapshot)
+    }
+
+    fn restore(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        Self::restore_with_config(bytes, EngineConfig::default())
+    }
+
+    fn shutdown(self) -> impl Waiting<()> {
+        MapWaiting::new(self.stop(false), drop)
+    }
+
+    fn shutdown_graceful(self) -> impl Waiting<usize> {
+        self.stop(true)
+    }
+}
+
 impl Default for SimpleEngine {
     fn default() -> Self {
         Self::new()
@@ -0,0 +1,97 @@
+use crossbeam_channel::Receiver;
+use engine_base::{
+    describe::GraphDescription,
+    lifecycle::{Lifecycle, LifecycleError},
+    operators::{types::RType, InputRef, Signal},
+    snapshot::SnapshotError,
+    waiting::{MaybeWaiting, Waiting},
+    Engine, ListenOptions,
+};
+
+use crate::{propagate::Propagator, EngineConfig, EngineSender, SimpleEngine};
+
+// Shares the engine thread with `SimpleEngine`, but evaluates independent nodes of each update on
+// a pool of propagation workers.
+pub struct ParallelEngine(SimpleEngine);
+
+impl ParallelEngine {
+    pub fn new() -> Self {
+        Self::with_config(EngineConfig::default())
+    }
+
+    pub fn with_config(config: EngineConfig) -> Self {
+        Self::with_threads(config, 0)
+    }
+
+    // Zero threads picks one worker per available core.
+    pub fn with_threads(config: EngineConfig, threads: usize) -> Self {
+        Self(SimpleEngine::with_propagator(config, move || {
+            Propagator::parallel(threads)
+        }))
+    }
+
+    /// # Errors
+    ///
+    /// Fails like `Engine::restore`.
+    pub fn restore_with_config(bytes: &[u8], config: EngineConfig) -> Result<Self, SnapshotError> {
+        SimpleEngine::restore_with_propagator(bytes, config, || Propagator::parallel(0)).map(Self)
+    }
+}
+
+impl Engine for ParallelEngine {
+    type Sender<T: RType> = EngineSender<T>;
+
+    fn lifecycle(&self) -> Lifecycle {
+        self.0.lifecycle()
+    }
+
+    fn start(&self) -> impl MaybeWaiting<Result<(), LifecycleError>> {
+        self.0.start()
+    }
+
+    fn pause(&self) -> impl MaybeWaiting<Result<(), LifecycleError>> {
+        self.0.pause()
+    }
+
+    fn resume(&self) -> impl MaybeWaiting<Result<(), LifecycleError>> {
+        self.0.resume()
+    }
+
+    fn shutdown(self) -> impl Waiting<()> {
+        self.0.shutdown()
+    }
+
+    fn shutdown_graceful(self) -> impl Waiting<usize> {
+        self.0.shutdown_graceful()
+    }
+
+    fn listen_with<T: RType>(
+        &self,
+        signal: Signal<T>,
+        options: ListenOptions,
+    ) -> impl MaybeWaiting<Receiver<T>> {
+        self.0.listen_with(signal, options)
+    }
+
+    fn emit<T: RType>(&self, input: InputRef) -> impl MaybeWaiting<EngineSender<T>> {
+        self.0.emit(input)
+    }
+
+    fn describe(&self) -> impl Waiting<GraphDescription> {
+        self.0.describe()
+    }
+
+    fn snapshot(&self) -> impl Waiting<Result<Vec<u8>, SnapshotError>> {
+        self.0.snapshot()
+    }
+
+    fn restore(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        Self::restore_with_config(bytes, EngineConfig::default())
+    }
+}
+
+impl Default for ParallelEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,187 @@
+use std::collections::BTreeSet;
+
+use engine_base::operators::types::Wrapper;
+use rayon::{prelude::*, ThreadPool, ThreadPoolBuilder};
+use rustc_hash::FxHashMap;
+
+use crate::{internal::Node, Apt};
+
+pub enum Propagator {
+    Sequential,
+    // Evaluates dirty nodes level by level, where a node's level is above all of its
+    // dependencies, so every node in a level only reads values from finished levels.
+    Parallel(ThreadPool),
+}
+
+// Dirty nodes ordered by height, so the first one is always ready to be evaluated.
+type Dirty = BTreeSet<(usize, usize)>;
+
+pub struct Graph<'g> {
+    pub fields: &'g mut [Wrapper],
+    pub nodes: &'g mut [Node],
+    pub dependents: &'g mut [Vec<usize>],
+    pub heights: &'g mut [usize],
+    pub signals: &'g FxHashMap<Apt, usize>,
+}
+
+impl Graph<'_> {
+    // Points a switch at the signal its selector holds, moving it above its new target when
+    // needed. Returns false when the switch was moved and has to wait for its new level.
+    pub fn rewire(&mut self, id: usize, dirty: &mut Dirty) -> bool {
+        let Node::Switch(selector, current) = self.nodes[id] else {
+            return true;
+        };
+        let target = selected(self.signals, &self.fields[selector]);
+        if target == current {
+            return true;
+        }
+        // A switch cannot follow a signal that is built on the switch itself.
+        let target = target.filter(|target| !self.reaches(id, *target));
+        if target == current {
+            return true;
+        }
+        if let Some(current) = current {
+            self.dependents[current].retain(|dependent| *dependent != id);
+        }
+        self.nodes[id] = Node::Switch(selector, target);
+        let Some(target) = target else {
+            return true;
+        };
+        self.dependents[target].push(id);
+        if self.heights[target] < self.heights[id] {
+            return true;
+        }
+        self.raise(id, self.heights[target] + 1, dirty);
+        dirty.insert((self.heights[id], id));
+        false
+    }
+
+    // Heights only ever grow, so they stay above every dependency without a full recount.
+    fn raise(&mut self, id: usize, height: usize, dirty: &mut Dirty) {
+        if self.heights[id] >= height {
+            return;
+        }
+        if dirty.remove(&(self.heights[id], id)) {
+            dirty.insert((height, id));
+        }
+        self.heights[id] = height;
+        for index in 0..self.dependents[id].len() {
+            self.raise(self.dependents[id][index], height + 1, dirty);
+        }
+    }
+
+    fn reaches(&self, from: usize, to: usize) -> bool {
+        let mut visited = vec![false; self.nodes.len()];
+        let mut stack = vec![from];
+        while let Some(id) = stack.pop() {
+            if id == to {
+                return true;
+            }
+            if !std::mem::replace(&mut visited[id], true) {
+                stack.extend(self.dependents[id].iter().copied());
+            }
+        }
+        false
+    }
+
+    fn schedule(&self, id: usize, dirty: &mut Dirty) {
+        for &dependent in &self.dependents[id] {
+            dirty.insert((self.heights[dependent], dependent));
+        }
+    }
+}
+
+// The node of the signal a selector holds, if that signal is registered.
+pub fn selected(signals: &FxHashMap<Apt, usize>, value: &Wrapper) -> Option<usize> {
+    match value {
+        Wrapper::Signal(signal) => signals.get(signal).copied(),
+        _ => None,
+    }
+}
+
+impl Propagator {
+    pub fn parallel(threads: usize) -> Self {
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .thread_name(|index| format!("propagate-{index}"))
+            .build()
+            .expect("Failed to start propagation workers");
+        Propagator::Parallel(pool)
+    }
+
+    // Calls `notify` once for every node affected by a new value of `input`, and returns how
+    // many nodes that was.
+    pub fn propagate(
+        &self,
+        mut graph: Graph<'_>,
+        input: usize,
+        mut notify: impl FnMut(usize, &Wrapper),
+    ) -> usize {
+        match self {
+            Propagator::Sequential => Self::sequential(graph, input, notify),
+            Propagator::Parallel(pool) => {
+                notify(input, &graph.fields[input]);
+                let mut dirty = Dirty::new();
+                let mut touched = 1;
+                graph.schedule(input, &mut dirty);
+                while let Some(&(height, _)) = dirty.first() {
+                    let mut ids = Vec::new();
+                    while let Some(&(level, id)) = dirty.first() {
+                        if level != height {
+                            break;
+                        }
+                        dirty.pop_first();
+                        ids.push(id);
+                    }
+                    ids.retain(|&id| graph.rewire(id, &mut dirty));
+                    let fields: &[Wrapper] = graph.fields;
+                    let nodes: &[Node] = graph.nodes;
+                    let values = if ids.len() == 1 {
+                        vec![nodes[ids[0]].evaluate(ids[0], |id| fields[id].clone())]
+                    } else {
+                        pool.install(|| {
+                            ids.par_iter()
+                                .map(|&id| nodes[id].evaluate(id, |id| fields[id].clone()))
+                                .collect()
+                        })
+                    };
+                    for (id, value) in ids.into_iter().zip(values) {
+                        if !graph.nodes[id].fires(&graph.fields[id], &value) {
+                            continue;
+                        }
+                        graph.fields[id] = value;
+                        touched += 1;
+                        notify(id, &graph.fields[id]);
+                        graph.schedule(id, &mut dirty);
+                    }
+                }
+                touched
+            }
+        }
+    }
+
+    fn sequential(
+        mut graph: Graph<'_>,
+        input: usize,
+        mut notify: impl FnMut(usize, &Wrapper),
+    ) -> usize {
+        let mut dirty = Dirty::from([(graph.heights[input], input)]);
+        let mut touched = 0;
+        while let Some((_, id)) = dirty.pop_first() {
+            if id != input {
+                if !graph.rewire(id, &mut dirty) {
+                    continue;
+                }
+                let value = graph.nodes[id].evaluate(id, |id| graph.fields[id].clone());
+                if !graph.nodes[id].fires(&graph.fields[id], &value) {
+                    continue;
+                }
+                graph.fields[id] = value;
+            }
+            touched += 1;
+            notify(id, &graph.fields[id]);
+            graph.schedule(id, &mut dirty);
+        }
+        touched
+    }
+}
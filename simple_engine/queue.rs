@@ -0,0 +1,131 @@
+use std::collections::VecDeque;
+
+use engine_base::{
+    observer::{EngineObserver, QueueKind, QueueOverflow},
+    operators::types::Wrapper,
+};
+use rustc_hash::FxHashMap;
+
+use crate::commands::Update;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Coalesce {
+    #[default]
+    KeepAll,
+    LatestPerInput,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    #[default]
+    DropOldest,
+    Reject,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct QueueConfig {
+    pub capacity: Option<usize>,
+    pub coalesce: Coalesce,
+    pub overflow: Overflow,
+}
+
+pub enum Pushed {
+    Queued,
+    Coalesced,
+    Evicted,
+}
+
+pub struct UpdateQueue {
+    kind: QueueKind,
+    config: QueueConfig,
+    updates: VecDeque<Update>,
+    popped: usize,
+    // Absolute position of the queued update for each input, used for coalescing.
+    positions: FxHashMap<usize, usize>,
+}
+
+impl UpdateQueue {
+    pub fn new(kind: QueueKind, config: QueueConfig) -> Self {
+        Self {
+            kind,
+            config,
+            updates: VecDeque::new(),
+            popped: 0,
+            positions: FxHashMap::default(),
+        }
+    }
+
+    pub fn kind(&self) -> QueueKind {
+        self.kind
+    }
+
+    pub fn len(&self) -> usize {
+        self.updates.len()
+    }
+
+    pub fn config(&self) -> QueueConfig {
+        self.config
+    }
+
+    // The overflow an update of the input would be rejected with, if it would be.
+    pub fn rejects(&self, input_pos: usize) -> Option<QueueOverflow> {
+        let coalesced = self.config.coalesce == Coalesce::LatestPerInput
+            && self.positions.contains_key(&input_pos);
+        let capacity = self.config.capacity.filter(|cap| self.len() >= *cap)?;
+        (self.config.overflow == Overflow::Reject && !coalesced).then_some(QueueOverflow {
+            queue: self.kind,
+            capacity,
+        })
+    }
+
+    pub fn push(&mut self, update: Update) -> Result<Pushed, QueueOverflow> {
+        if self.config.coalesce == Coalesce::LatestPerInput {
+            if let Some(position) = self.positions.get(&update.input_pos) {
+                // Maps keep the changes of every update they coalesce.
+                let queued = &mut self.updates[position - self.popped].value;
+                *queued = std::mem::replace(queued, Wrapper::Missing).coalesce(update.value);
+                return Ok(Pushed::Coalesced);
+            }
+        }
+        let mut res = Pushed::Queued;
+        if let Some(capacity) = self.config.capacity.filter(|cap| self.len() >= *cap) {
+            let overflow = QueueOverflow {
+                queue: self.kind,
+                capacity,
+            };
+            if self.config.overflow == Overflow::Reject || self.pop().is_none() {
+                return Err(overflow);
+            }
+            res = Pushed::Evicted;
+        }
+        if self.config.coalesce == Coalesce::LatestPerInput {
+            self.positions
+                .insert(update.input_pos, self.popped + self.updates.len());
+        }
+        self.updates.push_back(update);
+        Ok(res)
+    }
+
+    // Pushes the update, and tells the observer what became of it.
+    pub fn enqueue(&mut self, observer: &dyn EngineObserver, update: Update) {
+        observer.update_queued(self.kind);
+        match self.push(update) {
+            Ok(Pushed::Queued | Pushed::Coalesced) => {}
+            Ok(Pushed::Evicted) => observer.update_dropped(self.kind),
+            Err(overflow) => {
+                observer.update_dropped(self.kind);
+                observer.queue_overflowed(overflow);
+            }
+        }
+        observer.queue_depth(self.kind, self.len());
+    }
+
+    pub fn pop(&mut self) -> Option<Update> {
+        let update = self.updates.pop_front()?;
+        if self.positions.get(&update.input_pos) == Some(&self.popped) {
+            self.positions.remove(&update.input_pos);
+        }
+        self.popped += 1;
+        Some(update)
+    }
+}
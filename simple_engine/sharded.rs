@@ -0,0 +1,407 @@
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use crossbeam_channel::Receiver;
+use crossbeam_utils::sync::Unparker;
+use engine_base::{
+    describe::GraphDescription,
+    lifecycle::{Lifecycle, LifecycleError, SharedLifecycle},
+    observer::{ArithmeticOverflow, CommandKind, EngineObserver, Propagation, QueueKind},
+    operators::{types::RType, InputRef, Signal},
+    snapshot::{Snapshot, SnapshotError},
+    waiting::{AllWaiting, MapWaiting, MaybeWaiting, ThreadJoinWaiting, Waiting},
+    Engine, ListenOptions,
+};
+use rustc_hash::FxHashMap;
+
+use crate::{
+    commands::Command, propagate::Propagator, transport::Gate, EngineConfig, EngineSender,
+    SimpleEngine,
+};
+
+// Runs every connected component of the graph on its own `SimpleEngine`, so updates to unrelated
+// inputs are propagated concurrently. Shards are merged when a signal joins their components.
+pub struct ShardedEngine {
+    config: EngineConfig,
+    lifecycle: SharedLifecycle,
+    gate: Arc<Gate>,
+    shards: Mutex<Shards>,
+}
+
+#[derive(Default)]
+struct Shards {
+    // Merged shards leave an empty slot behind, so shard indices stay stable.
+    engines: Vec<Option<SimpleEngine>>,
+    owners: FxHashMap<InputRef, usize>,
+    // Switches can follow any signal, so once one exists every signal shares a single shard.
+    dynamic: bool,
+}
+
+impl Shards {
+    fn route(&mut self, inputs: &[InputRef], spawn: impl FnOnce() -> SimpleEngine) -> usize {
+        let mut owners = if self.dynamic {
+            (0..self.engines.len())
+                .filter(|shard| self.engines[*shard].is_some())
+                .collect()
+        } else {
+            inputs
+                .iter()
+                .filter_map(|input| self.owners.get(input).copied())
+                .collect::<Vec<_>>()
+        };
+        owners.sort_unstable();
+        owners.dedup();
+        let target = if let Some(target) = owners.first() {
+            *target
+        } else {
+            self.engines.push(Some(spawn()));
+            self.engines.len() - 1
+        };
+        for &other in &owners[owners.len().min(1)..] {
+            let detached = self.engines[other]
+                .take()
+                .expect("Shard was already merged")
+                .detach();
+            self.engine(target).absorb(detached).wait();
+            for owner in self.owners.values_mut() {
+                if *owner == other {
+                    *owner = target;
+                }
+            }
+        }
+        for input in inputs {
+            self.owners.insert(*input, target);
+        }
+        target
+    }
+
+    fn engine(&self, shard: usize) -> &SimpleEngine {
+        self.engines[shard]
+            .as_ref()
+            .expect("Shard was already merged")
+    }
+
+    fn live(&self) -> impl Iterator<Item = &SimpleEngine> {
+        self.engines.iter().flatten()
+    }
+}
+
+impl ShardedEngine {
+    pub fn new() -> Self {
+        Self::with_config(EngineConfig::default())
+    }
+
+    pub fn with_config(config: EngineConfig) -> Self {
+        let res = Self {
+            config,
+            lifecycle: SharedLifecycle::new(),
+            gate: Gate::new(),
+            shards: Mutex::default(),
+        };
+        res.hold(Lifecycle::Created);
+        res
+    }
+
+    /// # Errors
+    ///
+    /// Fails like `Engine::restore`.
+    pub fn restore_with_config(bytes: &[u8], config: EngineConfig) -> Result<Self, SnapshotError> {
+        let snapshot = Snapshot::decode(bytes)?;
+        let dynamic = snapshot
+            .nodes
+            .iter()
+            .any(|node| node.signal.desc.is_dynamic());
+        let mut components = Vec::<Option<Snapshot>>::new();
+        let mut owners = FxHashMap::<InputRef, usize>::default();
+        for node in snapshot.nodes {
+            let inputs = node.signal.desc.inputs();
+            let mut found = if dynamic {
+                (0..components.len())
+                    .filter(|component| components[*component].is_some())
+                    .collect()
+            } else {
+                inputs
+                    .iter()
+                    .filter_map(|input| owners.get(input).copied())
+                    .collect::<Vec<_>>()
+            };
+            found.sort_unstable();
+            found.dedup();
+            let target = if let Some(target) = found.first() {
+                *target
+            } else {
+                components.push(Some(Snapshot::default()));
+                components.len() - 1
+            };
+            // Components are disjoint, so appending one to another keeps dependencies first.
+            for &other in &found[found.len().min(1)..] {
+                let merged = components[other].take().expect("Component was merged");
+                components[target]
+                    .as_mut()
+                    .expect("Component was merged")
+                    .nodes
+                    .extend(merged.nodes);
+                for owner in owners.values_mut() {
+                    if *owner == other {
+                        *owner = target;
+                    }
+                }
+            }
+            for input in inputs {
+                owners.insert(input, target);
+            }
+            components[target]
+                .as_mut()
+                .expect("Component was merged")
+                .nodes
+                .push(node);
+        }
+        let gate = Gate::new();
+        let engines = components
+            .into_iter()
+            .map(|component| {
+                component.map(|component| {
+                    SimpleEngine::from_snapshot(
+                        component,
+                        shard_config(&config),
+                        || Propagator::Sequential,
+                        Arc::clone(&gate),
+                    )
+                })
+            })
+            .collect();
+        let res = Self {
+            config,
+            lifecycle: SharedLifecycle::new(),
+            gate,
+            shards: Mutex::new(Shards {
+                engines,
+                owners,
+                dynamic,
+            }),
+        };
+        res.hold(Lifecycle::Created);
+        Ok(res)
+    }
+
+    // The engine queues at its gate as soon as it goes to a state, before any shard does, so a
+    // shard that still queues after the engine stopped only holds updates the engine already took.
+    fn hold(&self, state: Lifecycle) {
+        let queue = match state {
+            Lifecycle::Created => Some((QueueKind::Prestart, self.config.prestart_queue)),
+            Lifecycle::Paused => Some((QueueKind::Pause, self.config.pause_queue)),
+            Lifecycle::Running | Lifecycle::ShuttingDown | Lifecycle::Stopped => None,
+        };
+        self.gate.hold(queue, &self.config.observer);
+    }
+
+    fn shards(&self) -> MutexGuard<'_, Shards> {
+        self.shards.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // Shards are only spawned while the registry is locked, so the lifecycle cannot change
+    // underneath.
+    fn spawn_shard(&self) -> SimpleEngine {
+        let engine = SimpleEngine::shard(shard_config(&self.config), Arc::clone(&self.gate));
+        match self.lifecycle.get() {
+            Lifecycle::Running => engine.start().wait().expect("New shard failed to start"),
+            Lifecycle::Paused => {
+                engine.start().wait().expect("New shard failed to start");
+                engine.pause().wait().expect("New shard failed to pause");
+            }
+            Lifecycle::Created | Lifecycle::ShuttingDown | Lifecycle::Stopped => {}
+        }
+        engine
+    }
+
+    fn broadcast(
+        &self,
+        from: Lifecycle,
+        to: Lifecycle,
+        command: impl Fn(Unparker) -> Command + Copy,
+    ) -> impl MaybeWaiting<Result<(), LifecycleError>> {
+        let shards = self.shards();
+        let result = self.lifecycle.transition_from(from, to);
+        let waiting = if result.is_ok() {
+            self.hold(to);
+            shards
+                .live()
+                .map(|engine| engine.transition(from, to, command))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        MapWaiting::new(
+            AllWaiting::from(waiting),
+            move |results: Vec<Result<(), LifecycleError>>| {
+                result.and(results.into_iter().collect())
+            },
+        )
+    }
+
+    fn stop(self, drain: bool) -> AllWaiting<ThreadJoinWaiting<usize>> {
+        let _ = self.lifecycle.transition(Lifecycle::ShuttingDown);
+        if drain {
+            self.hold(Lifecycle::ShuttingDown);
+        }
+        self.gate.close();
+        let shards = self
+            .shards
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner);
+        AllWaiting::from(
+            shards
+                .engines
+                .into_iter()
+                .flatten()
+                .map(|engine| engine.stop(drain))
+                .collect::<Vec<_>>(),
+        )
+    }
+}
+
+impl Engine for ShardedEngine {
+    type Sender<T: RType> = EngineSender<T>;
+
+    fn lifecycle(&self) -> Lifecycle {
+        self.lifecycle.get()
+    }
+
+    fn start(&self) -> impl MaybeWaiting<Result<(), LifecycleError>> {
+        self.broadcast(Lifecycle::Created, Lifecycle::Running, Command::Start)
+    }
+
+    fn pause(&self) -> impl MaybeWaiting<Result<(), LifecycleError>> {
+        self.broadcast(Lifecycle::Running, Lifecycle::Paused, Command::Pause)
+    }
+
+    fn resume(&self) -> impl MaybeWaiting<Result<(), LifecycleError>> {
+        self.broadcast(Lifecycle::Paused, Lifecycle::Running, Command::Resume)
+    }
+
+    fn shutdown(self) -> impl Waiting<()> {
+        MapWaiting::new(self.stop(false), drop)
+    }
+
+    fn shutdown_graceful(self) -> impl Waiting<usize> {
+        MapWaiting::new(self.stop(true), |drained: Vec<usize>| {
+            drained.into_iter().sum()
+        })
+    }
+
+    fn listen_with<T: RType>(
+        &self,
+        signal: Signal<T>,
+        options: ListenOptions,
+    ) -> impl MaybeWaiting<Receiver<T>> {
+        let mut shards = self.shards();
+        let desc = signal.get_desc();
+        shards.dynamic |= desc.desc.is_dynamic();
+        let shard = shards.route(&desc.desc.inputs(), || self.spawn_shard());
+        shards.engine(shard).add_listener(signal, options)
+    }
+
+    fn emit<T: RType>(&self, input: InputRef) -> impl MaybeWaiting<EngineSender<T>> {
+        let mut shards = self.shards();
+        let shard = shards.route(&[input], || self.spawn_shard());
+        shards.engine(shard).add_emitter(input)
+    }
+
+    fn describe(&self) -> impl Waiting<GraphDescription> {
+        let waiting = self
+            .shards()
+            .live()
+            .map(|engine| engine.request(Command::Describe))
+            .collect::<Vec<_>>();
+        MapWaiting::new(
+            AllWaiting::from(waiting),
+            |descriptions: Vec<GraphDescription>| {
+                let mut nodes = Vec::new();
+                for description in descriptions {
+                    let offset = nodes.len();
+                    nodes.extend(description.nodes.into_iter().map(|mut node| {
+                        node.id += offset;
+                        for dependency in &mut node.dependencies {
+                            *dependency += offset;
+                        }
+                        node
+                    }));
+                }
+                GraphDescription { nodes }
+            },
+        )
+    }
+
+    fn snapshot(&self) -> impl Waiting<Result<Vec<u8>, SnapshotError>> {
+        let waiting = self
+            .shards()
+            .live()
+            .map(|engine| engine.request(Command::Snapshot))
+            .collect::<Vec<_>>();
+        MapWaiting::new(
+            AllWaiting::from(waiting),
+            |snapshots: Vec<Result<Vec<u8>, SnapshotError>>| {
+                let mut res = Snapshot::default();
+                for bytes in snapshots {
+                    res.nodes.extend(Snapshot::decode(&bytes?)?.nodes);
+                }
+                res.encode()
+            },
+        )
+    }
+
+    fn restore(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        Self::restore_with_config(bytes, EngineConfig::default())
+    }
+}
+
+// Shards only queue updates that raced a transition, which the engine already queued at its gate,
+// so their queues take every update and keep quiet about it.
+fn shard_config(config: &EngineConfig) -> EngineConfig {
+    let mut res = config.clone();
+    res.prestart_queue.capacity = None;
+    res.pause_queue.capacity = None;
+    res.observer = Arc::new(ShardObserver(Arc::clone(&config.observer)));
+    res
+}
+
+struct ShardObserver(Arc<dyn EngineObserver>);
+
+impl EngineObserver for ShardObserver {
+    fn command_received(&self, command: CommandKind) {
+        self.0.command_received(command);
+    }
+
+    fn lifecycle_changed(&self, from: Lifecycle, to: Lifecycle) {
+        self.0.lifecycle_changed(from, to);
+    }
+
+    fn lifecycle_rejected(&self, error: LifecycleError) {
+        self.0.lifecycle_rejected(error);
+    }
+
+    fn update_propagated(&self, propagation: Propagation) {
+        self.0.update_propagated(propagation);
+    }
+
+    fn listener_removed(&self, node: usize) {
+        self.0.listener_removed(node);
+    }
+
+    fn emitter_disconnected(&self, input: InputRef) {
+        self.0.emitter_disconnected(input);
+    }
+
+    fn arithmetic_overflowed(&self, node: usize, overflow: ArithmeticOverflow) {
+        self.0.arithmetic_overflowed(node, overflow);
+    }
+
+    fn live_listeners(&self, count: usize) {
+        self.0.live_listeners(count);
+    }
+}
+
+impl Default for ShardedEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
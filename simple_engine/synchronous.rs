@@ -0,0 +1,259 @@
+use std::{
+    collections::VecDeque,
+    marker::PhantomData,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use crossbeam_channel::{Receiver, SendError};
+use engine_base::{
+    describe::GraphDescription,
+    lifecycle::{Lifecycle, LifecycleError, SharedLifecycle},
+    operators::{types::RType, InputRef, Signal},
+    snapshot::{Snapshot, SnapshotError},
+    waiting::{MaybeWaiting, ReadyWaiting, Waiting},
+    Emit, Engine, ListenOptions,
+};
+
+use crate::{
+    commands::Update,
+    internal::{Impl, Source},
+    propagate::Propagator,
+    transport::ListenerImpl,
+    EngineConfig,
+};
+
+// Runs the engine in the calling thread: every call returns once its effects are visible to
+// listeners, so the order of events is fully determined by the caller.
+pub struct SyncEngine {
+    state: Arc<Mutex<State>>,
+    lifecycle: Arc<SharedLifecycle>,
+}
+
+struct State {
+    engine: Impl<'static>,
+    // Present when emitted values wait for `step` instead of propagating right away.
+    pending: Option<VecDeque<Update>>,
+}
+
+fn lock(state: &Mutex<State>) -> MutexGuard<'_, State> {
+    state.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl SyncEngine {
+    pub fn new() -> Self {
+        Self::with_config(EngineConfig::default())
+    }
+
+    pub fn with_config(config: EngineConfig) -> Self {
+        Self::create(
+            |lifecycle| Impl::new(config, Propagator::Sequential, lifecycle),
+            false,
+        )
+    }
+
+    pub fn stepped(config: EngineConfig) -> Self {
+        Self::create(
+            |lifecycle| Impl::new(config, Propagator::Sequential, lifecycle),
+            true,
+        )
+    }
+
+    /// # Errors
+    ///
+    /// Fails like `Engine::restore`.
+    pub fn restore_with_config(bytes: &[u8], config: EngineConfig) -> Result<Self, SnapshotError> {
+        let snapshot = Snapshot::decode(bytes)?;
+        Ok(Self::create(
+            |lifecycle| Impl::restore(snapshot, config, Propagator::Sequential, lifecycle),
+            false,
+        ))
+    }
+
+    fn create(engine: impl FnOnce(Arc<SharedLifecycle>) -> Impl<'static>, stepped: bool) -> Self {
+        let lifecycle = Arc::new(SharedLifecycle::new());
+        let state = State {
+            engine: engine(Arc::clone(&lifecycle)),
+            pending: stepped.then(VecDeque::new),
+        };
+        Self {
+            state: Arc::new(Mutex::new(state)),
+            lifecycle,
+        }
+    }
+
+    // Propagates the oldest emitted value, returning false when there was none.
+    pub fn step(&self) -> bool {
+        let mut state = lock(&self.state);
+        let Some(update) = state.pending.as_mut().and_then(VecDeque::pop_front) else {
+            return false;
+        };
+        state.engine.accept_update(update);
+        true
+    }
+
+    pub fn run_until_idle(&self) -> usize {
+        let mut steps = 0;
+        while self.step() {
+            steps += 1;
+        }
+        steps
+    }
+
+    fn transition(
+        &self,
+        from: Lifecycle,
+        to: Lifecycle,
+        apply: impl FnOnce(&mut Impl<'static>),
+    ) -> ReadyWaiting<Result<(), LifecycleError>> {
+        let mut state = lock(&self.state);
+        let result = self.lifecycle.transition_from(from, to);
+        if result.is_ok() {
+            apply(&mut state.engine);
+        }
+        ReadyWaiting::from(result)
+    }
+
+    fn stop(self, drain: bool) -> usize {
+        let mut state = lock(&self.state);
+        let _ = self.lifecycle.transition(Lifecycle::ShuttingDown);
+        if let Some(pending) = state.pending.take() {
+            if drain {
+                for update in pending {
+                    state.engine.accept_update(update);
+                }
+            }
+        }
+        state.engine.shutdown(drain);
+        state.engine.finish()
+    }
+}
+
+impl Engine for SyncEngine {
+    type Sender<T: RType> = SyncSender<T>;
+
+    fn lifecycle(&self) -> Lifecycle {
+        self.lifecycle.get()
+    }
+
+    fn start(&self) -> impl MaybeWaiting<Result<(), LifecycleError>> {
+        self.transition(Lifecycle::Created, Lifecycle::Running, Impl::start)
+    }
+
+    fn pause(&self) -> impl MaybeWaiting<Result<(), LifecycleError>> {
+        self.transition(Lifecycle::Running, Lifecycle::Paused, Impl::pause)
+    }
+
+    fn resume(&self) -> impl MaybeWaiting<Result<(), LifecycleError>> {
+        self.transition(Lifecycle::Paused, Lifecycle::Running, Impl::resume)
+    }
+
+    fn shutdown(self) -> impl Waiting<()> {
+        self.stop(false);
+        ReadyWaiting::from(())
+    }
+
+    fn shutdown_graceful(self) -> impl Waiting<usize> {
+        ReadyWaiting::from(self.stop(true))
+    }
+
+    fn listen_with<T: RType>(
+        &self,
+        signal: Signal<T>,
+        options: ListenOptions,
+    ) -> impl MaybeWaiting<Receiver<T>> {
+        let (s, r) = crossbeam_channel::unbounded();
+        lock(&self.state).engine.add_listener(
+            signal.get_desc(),
+            Box::new(ListenerImpl::new(s)),
+            options,
+        );
+        ReadyWaiting::from(r)
+    }
+
+    fn emit<T: RType>(&self, input: InputRef) -> impl MaybeWaiting<SyncSender<T>> {
+        let mut state = lock(&self.state);
+        let field = state.engine.input_field(input, T::into_type());
+        let id = state.engine.register_emitter(field, Source::Caller);
+        ReadyWaiting::from(SyncSender {
+            emitter: Arc::new(EmitterHandle {
+                state: Arc::clone(&self.state),
+                id,
+                field,
+            }),
+            value: PhantomData,
+        })
+    }
+
+    fn describe(&self) -> impl Waiting<GraphDescription> {
+        ReadyWaiting::from(lock(&self.state).engine.describe())
+    }
+
+    fn snapshot(&self) -> impl Waiting<Result<Vec<u8>, SnapshotError>> {
+        ReadyWaiting::from(lock(&self.state).engine.snapshot())
+    }
+
+    fn restore(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        Self::restore_with_config(bytes, EngineConfig::default())
+    }
+}
+
+impl Default for SyncEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct EmitterHandle {
+    state: Arc<Mutex<State>>,
+    id: usize,
+    field: usize,
+}
+
+impl Drop for EmitterHandle {
+    fn drop(&mut self) {
+        let mut state = lock(&self.state);
+        if state.engine.state() != Lifecycle::Stopped {
+            state.engine.disconnect_emitter(self.id);
+        }
+    }
+}
+
+// Clones share one emitter, which disconnects when the last of them is dropped.
+pub struct SyncSender<T> {
+    emitter: Arc<EmitterHandle>,
+    value: PhantomData<fn(T)>,
+}
+
+impl<T> Clone for SyncSender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            emitter: Arc::clone(&self.emitter),
+            value: PhantomData,
+        }
+    }
+}
+
+impl<T: RType> Emit<T> for SyncSender<T> {
+    fn send(&self, value: T) -> Result<(), SendError<T>> {
+        let mut state = lock(&self.emitter.state);
+        if matches!(
+            state.engine.state(),
+            Lifecycle::ShuttingDown | Lifecycle::Stopped
+        ) {
+            return Err(SendError(value));
+        }
+        // Stepped engines decide once the update is stepped.
+        if state.pending.is_none() && !state.engine.admits(self.emitter.field) {
+            return Err(SendError(value));
+        }
+        let update = Update {
+            input_pos: self.emitter.field,
+            value: value.wrap(),
+        };
+        match &mut state.pending {
+            Some(pending) => pending.push_back(update),
+            None => state.engine.accept_update(update),
+        }
+        Ok(())
+    }
+}
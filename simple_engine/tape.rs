@@ -0,0 +1,339 @@
+use engine_base::operators::types::{Overflow, Type, Wrapper};
+
+use crate::internal::Node;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Evaluator {
+    // Walks the dependents of every updated input and dispatches on each node and value.
+    #[default]
+    Interpreted,
+    // Runs a precompiled tape of typed instructions per input, which also holds every node value.
+    // The tape takes precedence over parallel propagation. A switch pointed at another signal
+    // recompiles the tapes before its run carries on.
+    Compiled,
+}
+
+// Where an engine keeps the values of its nodes.
+pub enum Fields {
+    Boxed(Vec<Wrapper>),
+    Compiled(Box<Tape>),
+}
+
+impl Fields {
+    pub fn new(evaluator: Evaluator) -> Self {
+        match evaluator {
+            Evaluator::Interpreted => Fields::Boxed(Vec::new()),
+            Evaluator::Compiled => Fields::Compiled(Box::default()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Fields::Boxed(fields) => fields.len(),
+            Fields::Compiled(tape) => tape.slots.len(),
+        }
+    }
+
+    pub fn get(&self, id: usize) -> Wrapper {
+        match self {
+            Fields::Boxed(fields) => fields[id].clone(),
+            Fields::Compiled(tape) => tape.get(id),
+        }
+    }
+
+    pub fn set(&mut self, id: usize, value: Wrapper) {
+        match self {
+            Fields::Boxed(fields) => fields[id] = value,
+            Fields::Compiled(tape) => tape.set(id, &value),
+        }
+    }
+
+    pub fn push(&mut self, node: &Node, rtype: Type, value: Wrapper) {
+        match self {
+            Fields::Boxed(fields) => fields.push(value),
+            Fields::Compiled(tape) => tape.push(node, rtype, &value),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Slot {
+    U64(usize),
+    // Values that are only ever passed along, kept as shared wrappers.
+    Shared(usize),
+}
+
+#[derive(Clone)]
+enum Instruction {
+    Input,
+    AddU64 {
+        add: fn(u64, u64) -> u64,
+        left: usize,
+        right: usize,
+        out: usize,
+    },
+    // Anything without a specialized instruction runs through the interpreter.
+    Node(Node),
+}
+
+// Node values unboxed into one column per type, with the nodes an update of each input reaches
+// compiled into a list in topological order. Nodes only ever get appended after their
+// dependencies, so compiling a new node appends it to the tapes of the inputs it reads. Switches
+// that point at another signal can break that order, so they recompile every tape by height.
+#[derive(Default)]
+pub struct Tape {
+    u64s: Vec<u64>,
+    shared: Vec<Wrapper>,
+    slots: Vec<Slot>,
+    instructions: Vec<Instruction>,
+    dependencies: Vec<Vec<usize>>,
+    triggers: Vec<Vec<usize>>,
+    sources: Vec<Vec<usize>>,
+    tapes: Vec<Vec<usize>>,
+    // Whether a tape goes through events that may not occur, so its nodes only run when one of
+    // their triggers fired during the same run.
+    gated: Vec<bool>,
+    // The last run each node fired in, and the last one it was evaluated in.
+    fired: Vec<u64>,
+    visited: Vec<u64>,
+    runs: u64,
+    // Switches that were pointed at another node, and the node they point at now.
+    rewired: Vec<(usize, Option<usize>)>,
+}
+
+impl Tape {
+    pub fn push(&mut self, node: &Node, rtype: Type, value: &Wrapper) {
+        let id = self.slots.len();
+        // A `u64` node that holds a value only goes missing again when one of its operands does,
+        // or when it overflows, so only nodes reading unboxed operands are unboxed.
+        let unboxed = !matches!(node, Node::Add(Overflow::Checked, ..))
+            && node
+                .dependencies()
+                .into_iter()
+                .all(|dependency| matches!(self.slots[dependency], Slot::U64(_)));
+        let slot = if let (Type::U64, Wrapper::U64(value), true) = (rtype, value, unboxed) {
+            self.u64s.push(*value);
+            Slot::U64(self.u64s.len() - 1)
+        } else {
+            self.shared.push(value.clone());
+            Slot::Shared(self.shared.len() - 1)
+        };
+        let instruction = match node {
+            Node::Input => Instruction::Input,
+            Node::Add(overflow, left, right) => {
+                let add = match overflow {
+                    Overflow::Wrapping => Some(u64::wrapping_add as fn(u64, u64) -> u64),
+                    Overflow::Saturating => Some(u64::saturating_add as fn(u64, u64) -> u64),
+                    // Overflows have to be reported, so they go through the interpreter.
+                    Overflow::Checked => None,
+                };
+                match (add, self.slots[*left], self.slots[*right], slot) {
+                    (Some(add), Slot::U64(left), Slot::U64(right), Slot::U64(out)) => {
+                        Instruction::AddU64 {
+                            add,
+                            left,
+                            right,
+                            out,
+                        }
+                    }
+                    _ => Instruction::Node(node.clone()),
+                }
+            }
+            node => Instruction::Node(node.clone()),
+        };
+        let mut sources = if let Node::Input = node {
+            vec![id]
+        } else {
+            node.triggers()
+                .into_iter()
+                .flat_map(|dependency| self.sources[dependency].iter().copied())
+                .collect()
+        };
+        sources.sort_unstable();
+        sources.dedup();
+        for &source in &sources {
+            if source != id {
+                self.tapes[source].push(id);
+                self.gated[source] |= matches!(node, Node::Changes(_));
+            }
+        }
+        self.slots.push(slot);
+        self.instructions.push(instruction);
+        self.dependencies.push(node.dependencies());
+        self.triggers.push(node.triggers());
+        self.sources.push(sources);
+        self.tapes.push(Vec::new());
+        self.gated.push(false);
+        self.fired.push(0);
+        self.visited.push(0);
+    }
+
+    pub fn get(&self, id: usize) -> Wrapper {
+        match self.slots[id] {
+            Slot::U64(slot) => Wrapper::U64(self.u64s[slot]),
+            Slot::Shared(slot) => self.shared[slot].clone(),
+        }
+    }
+
+    pub fn set(&mut self, id: usize, value: &Wrapper) {
+        match (self.slots[id], value) {
+            (Slot::U64(slot), Wrapper::U64(value)) => self.u64s[slot] = *value,
+            (Slot::Shared(slot), value) => self.shared[slot] = value.clone(),
+            (_, value) => unreachable!("{value:?} does not fit its slot"),
+        }
+    }
+
+    // Same contract as `Propagator::propagate`, with `select` finding the node a selected signal
+    // belongs to. Nodes are visited in tape order, and a switch pointed at another node starts
+    // the recompiled tape over, skipping the nodes this run already evaluated.
+    pub fn run(
+        &mut self,
+        input: usize,
+        select: impl Fn(&Wrapper) -> Option<usize>,
+        mut notify: impl FnMut(usize, Wrapper),
+    ) -> usize {
+        self.runs += 1;
+        let run = self.runs;
+        self.fired[input] = run;
+        notify(input, self.get(input));
+        let mut touched = 1;
+        let mut step = 0;
+        while step < self.tapes[input].len() {
+            let id = self.tapes[input][step];
+            step += 1;
+            if self.visited[id] == run
+                || self.gated[input]
+                    && !self.triggers[id].iter().any(|&trigger| self.fired[trigger] == run)
+            {
+                continue;
+            }
+            if self.rewire(id, &select) {
+                step = 0;
+                continue;
+            }
+            self.visited[id] = run;
+            let fires = match &self.instructions[id] {
+                Instruction::Input => true,
+                Instruction::AddU64 {
+                    add,
+                    left,
+                    right,
+                    out,
+                } => {
+                    self.u64s[*out] = add(self.u64s[*left], self.u64s[*right]);
+                    true
+                }
+                Instruction::Node(node) => {
+                    let value = node.evaluate(id, |id| self.get(id));
+                    let fires = node.fires(&self.get(id), &value);
+                    if fires {
+                        self.set(id, &value);
+                    }
+                    fires
+                }
+            };
+            if fires {
+                self.fired[id] = run;
+                touched += 1;
+                notify(id, self.get(id));
+            }
+        }
+        touched
+    }
+
+    // Points every switch at its selector's current signal, after values were set without a run.
+    pub fn rewire_all(&mut self, select: impl Fn(&Wrapper) -> Option<usize>) {
+        for id in 0..self.instructions.len() {
+            self.rewire(id, &select);
+        }
+    }
+
+    // The switches pointed at another node since the last call.
+    pub fn rewired(&mut self) -> Vec<(usize, Option<usize>)> {
+        std::mem::take(&mut self.rewired)
+    }
+
+    // Points a switch at the signal its selector holds, and recompiles the tapes when that is a
+    // different node than before.
+    fn rewire(&mut self, id: usize, select: impl Fn(&Wrapper) -> Option<usize>) -> bool {
+        let Instruction::Node(Node::Switch(selector, current)) = self.instructions[id] else {
+            return false;
+        };
+        // A switch cannot follow a signal that is built on the switch itself.
+        let target = select(&self.get(selector)).filter(|target| !self.reaches(*target, id));
+        if target == current {
+            return false;
+        }
+        let node = Node::Switch(selector, target);
+        self.dependencies[id] = node.dependencies();
+        self.triggers[id] = node.triggers();
+        self.instructions[id] = Instruction::Node(node);
+        self.rewired.push((id, target));
+        self.recompile();
+        true
+    }
+
+    // Whether `from` is built on `to`.
+    fn reaches(&self, from: usize, to: usize) -> bool {
+        let mut visited = vec![false; self.instructions.len()];
+        let mut stack = vec![from];
+        while let Some(id) = stack.pop() {
+            if id == to {
+                return true;
+            }
+            if !std::mem::replace(&mut visited[id], true) {
+                stack.extend(self.dependencies[id].iter().copied());
+            }
+        }
+        false
+    }
+
+    // Rebuilds every tape from the dependencies nodes have now, ordered by height since ids are
+    // no longer topological once a switch points at a later node.
+    fn recompile(&mut self) {
+        let count = self.instructions.len();
+        let mut heights = vec![0; count];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for id in 0..count {
+                let height = self.dependencies[id]
+                    .iter()
+                    .map(|dependency| heights[*dependency] + 1)
+                    .max()
+                    .unwrap_or(0);
+                changed |= height != heights[id];
+                heights[id] = height;
+            }
+        }
+        let mut dependents = vec![Vec::new(); count];
+        for (id, triggers) in self.triggers.iter().enumerate() {
+            for &trigger in triggers {
+                dependents[trigger].push(id);
+            }
+        }
+        for sources in &mut self.sources {
+            sources.clear();
+        }
+        for input in 0..count {
+            self.tapes[input].clear();
+            self.gated[input] = false;
+            if !matches!(self.instructions[input], Instruction::Input) {
+                continue;
+            }
+            self.sources[input].push(input);
+            let mut reached = vec![false; count];
+            let mut stack = dependents[input].clone();
+            while let Some(id) = stack.pop() {
+                if !std::mem::replace(&mut reached[id], true) {
+                    stack.extend(dependents[id].iter().copied());
+                    self.tapes[input].push(id);
+                    self.sources[id].push(input);
+                    self.gated[input] |=
+                        matches!(self.instructions[id], Instruction::Node(Node::Changes(_)));
+                }
+            }
+            self.tapes[input].sort_unstable_by_key(|&id| (heights[id], id));
+        }
+    }
+}
@@ -1,16 +1,36 @@
-use crossbeam_channel::{Receiver, RecvError, Select, SelectedOperation, Sender};
-use engine_base::operators::types::{RType, Wrapper};
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+
+use crossbeam_channel::{
+    Receiver, RecvError, Select, SelectedOperation, SendError, Sender, TryRecvError,
+};
+use engine_base::{
+    observer::{EngineObserver, QueueKind, QueueOverflow},
+    operators::{
+        types::{RType, Wrapper},
+        InputRef,
+    },
+    Emit,
+};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{
+    commands::Update,
+    queue::{Coalesce, Overflow, QueueConfig, UpdateQueue},
+};
 
 #[derive(Debug)]
 pub struct ChannelClosed;
 
-pub trait Emitter {
+pub trait Emitter: Send + Sync {
     fn install<'a>(&'a self, select: &mut Select<'a>);
     fn receive(&self, op: SelectedOperation) -> Result<Wrapper, RecvError>;
+    fn try_receive(&self) -> Result<Wrapper, TryRecvError>;
+    fn pending(&self) -> usize;
+    fn detach(&self) -> Box<dyn Emitter + Send>;
 }
 
 pub trait Listener {
-    fn accept(&self, wrapper: Wrapper) -> Result<(), ChannelClosed>;
+    fn accept(&self, wrapper: &Wrapper) -> Result<(), ChannelClosed>;
 }
 
 pub struct EmitterImpl<T> {
@@ -32,6 +52,208 @@ impl<T: RType> Emitter for EmitterImpl<T> {
         let wrapper = op.recv(&self.receiver)?.wrap();
         Ok(wrapper)
     }
+
+    fn try_receive(&self) -> Result<Wrapper, TryRecvError> {
+        Ok(self.receiver.try_recv()?.wrap())
+    }
+
+    fn pending(&self) -> usize {
+        self.receiver.len()
+    }
+
+    fn detach(&self) -> Box<dyn Emitter + Send> {
+        Box::new(EmitterImpl::new(self.receiver.clone()))
+    }
+}
+
+// Shared by an engine and all of its senders. Once it is closed no send can slip in, so a graceful
+// shutdown knows every message it has to drain.
+pub struct Gate {
+    open: RwLock<bool>,
+    // Present while updates go to a queue that rejects them once full, so senders can refuse
+    // those updates themselves and the caller hears about it.
+    admission: Mutex<Option<Admission>>,
+    // Present while a sharded engine queues. Each shard only sees its own inputs, so the engine
+    // queues updates here, before they reach any shard.
+    held: Mutex<Option<Held>>,
+}
+
+// Counts what senders let into a queue, which is empty whenever the engine starts queueing.
+// Updates already on their way when the engine pauses are not counted, so the engine still
+// rejects those on its own.
+struct Admission {
+    kind: QueueKind,
+    capacity: usize,
+    coalesce: bool,
+    inputs: FxHashSet<InputRef>,
+    queued: usize,
+    observer: Arc<dyn EngineObserver>,
+}
+
+// Updates are queued by the position of their input, which is also where its sender is kept.
+struct Held {
+    queue: UpdateQueue,
+    inputs: FxHashMap<InputRef, usize>,
+    senders: Vec<Box<dyn Fn(Wrapper) + Send>>,
+    observer: Arc<dyn EngineObserver>,
+}
+
+impl Held {
+    fn push<T: RType>(&mut self, input: InputRef, sender: &Sender<T>, value: T) -> Result<(), T> {
+        let senders = &mut self.senders;
+        let input_pos = *self.inputs.entry(input).or_insert_with(|| {
+            let sender = sender.clone();
+            // A shard that already stopped has no use for the update.
+            senders.push(Box::new(move |value| {
+                let _ = sender.send(T::coerce(value));
+            }));
+            senders.len() - 1
+        });
+        if let Some(overflow) = self.queue.rejects(input_pos) {
+            self.observer.update_dropped(overflow.queue);
+            self.observer.queue_overflowed(overflow);
+            return Err(value);
+        }
+        let value = value.wrap();
+        self.queue
+            .enqueue(&*self.observer, Update { input_pos, value });
+        Ok(())
+    }
+}
+
+impl Gate {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            open: RwLock::new(true),
+            admission: Mutex::default(),
+            held: Mutex::default(),
+        })
+    }
+
+    // Called by a sharded engine whenever it starts or stops queueing updates. Held updates are
+    // sent on once it stops, before any later update can overtake them.
+    pub fn hold(
+        &self,
+        queue: Option<(QueueKind, QueueConfig)>,
+        observer: &Arc<dyn EngineObserver>,
+    ) {
+        let mut held = self.held.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(mut released) = held.take() {
+            while let Some(Update { input_pos, value }) = released.queue.pop() {
+                (released.senders[input_pos])(value);
+            }
+            observer.queue_depth(released.queue.kind(), 0);
+        }
+        *held = queue.map(|(kind, config)| Held {
+            queue: UpdateQueue::new(kind, config),
+            inputs: FxHashMap::default(),
+            senders: Vec::new(),
+            observer: Arc::clone(observer),
+        });
+    }
+
+    pub fn close(&self) {
+        *self.open.write().unwrap_or_else(PoisonError::into_inner) = false;
+    }
+
+    // Called by the engine whenever it starts or stops queueing updates.
+    pub fn guard(&self, queue: Option<&UpdateQueue>, observer: &Arc<dyn EngineObserver>) {
+        let admission = queue.and_then(|queue| {
+            let config = queue.config();
+            let capacity = config
+                .capacity
+                .filter(|_| config.overflow == Overflow::Reject)?;
+            Some(Admission {
+                kind: queue.kind(),
+                capacity,
+                coalesce: config.coalesce == Coalesce::LatestPerInput,
+                inputs: FxHashSet::default(),
+                queued: 0,
+                observer: Arc::clone(observer),
+            })
+        });
+        *self
+            .admission
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = admission;
+    }
+
+    fn admits(&self, input: InputRef) -> bool {
+        let mut admission = self
+            .admission
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let Some(admission) = admission.as_mut() else {
+            return true;
+        };
+        if admission.coalesce && admission.inputs.contains(&input) {
+            return true;
+        }
+        if admission.queued >= admission.capacity {
+            admission.observer.update_dropped(admission.kind);
+            admission.observer.queue_overflowed(QueueOverflow {
+                queue: admission.kind,
+                capacity: admission.capacity,
+            });
+            return false;
+        }
+        admission.queued += 1;
+        if admission.coalesce {
+            admission.inputs.insert(input);
+        }
+        true
+    }
+}
+
+pub struct EngineSender<T> {
+    sender: Sender<T>,
+    gate: Arc<Gate>,
+    input: InputRef,
+}
+
+impl<T> EngineSender<T> {
+    pub fn new(sender: Sender<T>, gate: Arc<Gate>, input: InputRef) -> Self {
+        Self {
+            sender,
+            gate,
+            input,
+        }
+    }
+}
+
+impl<T> Clone for EngineSender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            gate: Arc::clone(&self.gate),
+            input: self.input,
+        }
+    }
+}
+
+impl<T: RType> Emit<T> for EngineSender<T> {
+    fn send(&self, value: T) -> Result<(), SendError<T>> {
+        // The read lock is held across the send, so closing waits for sends already under way.
+        let open = self
+            .gate
+            .open
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        if !*open || !self.gate.admits(self.input) {
+            return Err(SendError(value));
+        }
+        let mut held = self
+            .gate
+            .held
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        match held.as_mut() {
+            Some(held) => held
+                .push(self.input, &self.sender, value)
+                .map_err(SendError),
+            None => self.sender.send(value),
+        }
+    }
 }
 
 pub struct ListenerImpl<T> {
@@ -45,9 +267,15 @@ impl<T: RType> ListenerImpl<T> {
 }
 
 impl<T: RType> Listener for ListenerImpl<T> {
-    fn accept(&self, wrapper: Wrapper) -> Result<(), ChannelClosed> {
-        self.sender
-            .send(T::coerce(wrapper))
-            .map_err(|_| ChannelClosed)
+    // Listeners that cannot represent a missing value are not notified until there is one.
+    fn accept(&self, wrapper: &Wrapper) -> Result<(), ChannelClosed> {
+        let value = match wrapper {
+            Wrapper::Missing => match T::missing() {
+                Some(value) => value,
+                None => return Ok(()),
+            },
+            wrapper => T::coerce(wrapper.clone()),
+        };
+        self.sender.send(value).map_err(|_| ChannelClosed)
     }
 }
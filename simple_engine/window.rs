@@ -0,0 +1,147 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex, PoisonError},
+    time::Duration,
+};
+
+use engine_base::{
+    clock::Clock,
+    operators::{
+        collection::Collection,
+        types::Wrapper,
+        window::{Aggregate, Window},
+    },
+    snapshot::WindowHistory,
+};
+
+enum Kind {
+    Timed(Aggregate, Window),
+    Last(usize),
+}
+
+#[derive(Default)]
+struct History {
+    // Oldest first, along with when each value arrived.
+    values: VecDeque<(Duration, Wrapper)>,
+    // Wide enough that averages never see a wrapped sum.
+    sum: u128,
+    // How many values were ever recorded, which numbers the entries of `last_n`.
+    recorded: u64,
+}
+
+// The ring buffer behind a window node. Nodes are cloned into tapes, and graphs are handed over
+// between engine threads, so buffers are shared rather than owned by a node.
+pub struct Buffer {
+    kind: Kind,
+    clock: Arc<dyn Clock>,
+    history: Mutex<History>,
+}
+
+impl Buffer {
+    pub fn timed(aggregate: Aggregate, window: Window, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            kind: Kind::Timed(aggregate, window),
+            clock,
+            history: Mutex::default(),
+        }
+    }
+
+    pub fn last(n: usize, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            kind: Kind::Last(n),
+            clock,
+            history: Mutex::default(),
+        }
+    }
+
+    // The value of a window that has not seen any updates.
+    pub fn empty(&self) -> Wrapper {
+        match self.kind {
+            Kind::Timed(..) => Wrapper::U64(0),
+            Kind::Last(_) => Wrapper::Map(Arc::default()),
+        }
+    }
+
+    // Takes over the history of the same window from another engine thread.
+    pub fn adopt(&self, other: &Buffer) {
+        let history =
+            std::mem::take(&mut *other.history.lock().unwrap_or_else(PoisonError::into_inner));
+        *self.history.lock().unwrap_or_else(PoisonError::into_inner) = history;
+    }
+
+    pub fn history(&self) -> WindowHistory {
+        let history = self.history.lock().unwrap_or_else(PoisonError::into_inner);
+        WindowHistory {
+            values: history.values.iter().cloned().collect(),
+            recorded: history.recorded,
+        }
+    }
+
+    // Picks up a history written by `history`, as restored from a snapshot.
+    pub fn restore(&self, restored: WindowHistory) {
+        let mut history = self.history.lock().unwrap_or_else(PoisonError::into_inner);
+        history.sum = restored
+            .values
+            .iter()
+            .filter_map(|(_, value)| match value {
+                Wrapper::U64(value) => Some(u128::from(*value)),
+                _ => None,
+            })
+            .sum();
+        history.values = restored.values.into();
+        history.recorded = restored.recorded;
+    }
+
+    // Records a new value of the windowed signal, and returns what the window holds after it.
+    // Missing values are not recorded, but still move time windows forward.
+    pub fn record(&self, value: Wrapper, own: &Wrapper) -> Wrapper {
+        let now = self.clock.now();
+        let mut history = self.history.lock().unwrap_or_else(PoisonError::into_inner);
+        let present = value != Wrapper::Missing;
+        match self.kind {
+            Kind::Timed(aggregate, window) => {
+                if present {
+                    if let Wrapper::U64(value) = value {
+                        history.sum += u128::from(value);
+                    }
+                    history.values.push_back((now, value));
+                }
+                while let Some((then, value)) = history.values.front() {
+                    if window.contains(*then, now) {
+                        break;
+                    }
+                    if let Wrapper::U64(value) = value {
+                        history.sum -= u128::from(*value);
+                    }
+                    history.values.pop_front();
+                }
+                let count = history.values.len() as u128;
+                // Sums wrap around by dropping the high bits, and averages of `u64`s always fit.
+                #[allow(clippy::cast_possible_truncation)]
+                Wrapper::U64(match aggregate {
+                    Aggregate::Sum => history.sum as u64,
+                    Aggregate::Avg => history.sum.checked_div(count).unwrap_or(0) as u64,
+                    Aggregate::Count => count as u64,
+                })
+            }
+            Kind::Last(n) => {
+                let mut changes = Vec::new();
+                if present {
+                    changes.push((Wrapper::U64(history.recorded), Some(value.clone())));
+                    history.values.push_back((now, value));
+                    history.recorded += 1;
+                }
+                while history.values.len() > n {
+                    history.values.pop_front();
+                    let evicted = history.recorded - history.values.len() as u64 - 1;
+                    changes.push((Wrapper::U64(evicted), None));
+                }
+                let collection = match own {
+                    Wrapper::Map(collection) => collection.apply(changes),
+                    _ => Collection::default().apply(changes),
+                };
+                Wrapper::Map(Arc::new(collection))
+            }
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use rig_macros::test_suite;
+
+#[test_suite]
+pub mod add {
+
+    use engine_base::{
+        operators::{add, input},
+        waiting::Waiting,
+        Emit, Engine, ListenOptions,
+    };
+
+    const REPLAY: ListenOptions = ListenOptions {
+        replay_current: true,
+    };
+
+    #[setup]
+    fn setup<T: Engine>(e: T) {
+        let engine = e;
+        let (left_ref, left) = input::<u64>();
+        let (right_ref, right) = input::<u64>();
+    }
+
+    #[case]
+    pub fn add_propagates_both_inputs() {
+        let listener = engine.listen(add(left, right)).wait();
+        let left_emitter = engine.emit::<u64>(left_ref).wait();
+        let right_emitter = engine.emit::<u64>(right_ref).wait();
+        engine.start().wait()?;
+        left_emitter.send(40)?;
+        assert_eq!(listener.recv()?, 40);
+        right_emitter.send(2)?;
+        assert_eq!(listener.recv()?, 42);
+    }
+
+    #[case]
+    pub fn add_propagates_prestart_emissions() {
+        let listener = engine.listen(add(left, right)).wait();
+        let left_emitter = engine.emit::<u64>(left_ref).wait();
+        let right_emitter = engine.emit::<u64>(right_ref).wait();
+        left_emitter.send(40)?;
+        assert!(listener.try_recv().is_err());
+        engine.start().wait()?;
+        assert_eq!(listener.recv()?, 40);
+        right_emitter.send(2)?;
+        assert_eq!(listener.recv()?, 42);
+    }
+
+    #[case]
+    pub fn nested_add_propagates_through_shared_input() {
+        let doubled = add(left.clone(), left);
+        let listener = engine.listen(add(doubled, right)).wait();
+        let left_emitter = engine.emit::<u64>(left_ref).wait();
+        let right_emitter = engine.emit::<u64>(right_ref).wait();
+        engine.start().wait()?;
+        left_emitter.send(20)?;
+        assert_eq!(listener.recv()?, 40);
+        right_emitter.send(2)?;
+        assert_eq!(listener.recv()?, 42);
+    }
+
+    #[case]
+    pub fn add_and_input_listeners_both_notified() {
+        let sum = engine.listen(add(left.clone(), right)).wait();
+        let direct = engine.listen(left).wait();
+        let left_emitter = engine.emit::<u64>(left_ref).wait();
+        let _right_emitter = engine.emit::<u64>(right_ref).wait();
+        engine.start().wait()?;
+        left_emitter.send(42)?;
+        assert_eq!(direct.recv()?, 42);
+        assert_eq!(sum.recv()?, 42);
+    }
+
+    #[case]
+    pub fn wide_level_is_evaluated_once_per_update() {
+        let branches = vec![
+            add(left.clone(), right.clone()),
+            add(right.clone(), left.clone()),
+            add(left.clone(), left.clone()),
+            add(right.clone(), right),
+        ];
+        let listeners = branches
+            .iter()
+            .map(|branch| engine.listen(branch.clone()).wait())
+            .collect::<Vec<_>>();
+        let total = branches
+            .into_iter()
+            .reduce(add)
+            .expect("branches are not empty");
+        let listener = engine.listen(total).wait();
+        let left_emitter = engine.emit::<u64>(left_ref).wait();
+        let right_emitter = engine.emit::<u64>(right_ref).wait();
+        engine.start().wait()?;
+        left_emitter.send(3)?;
+        assert_eq!(listener.recv()?, 12);
+        right_emitter.send(2)?;
+        assert_eq!(listener.recv()?, 20);
+        let received = listeners
+            .iter()
+            .map(|branch| branch.try_iter().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(received, vec![vec![3, 5], vec![3, 5], vec![6], vec![4]]);
+        engine.describe().wait();
+        assert!(listener.try_recv().is_err());
+    }
+
+    #[case]
+    pub fn add_joins_inputs_used_separately() {
+        let left_listener = engine.listen(left.clone()).wait();
+        let right_listener = engine.listen(right.clone()).wait();
+        let left_emitter = engine.emit::<u64>(left_ref).wait();
+        let right_emitter = engine.emit::<u64>(right_ref).wait();
+        engine.start().wait()?;
+        left_emitter.send(40)?;
+        assert_eq!(left_listener.recv()?, 40);
+        right_emitter.send(1)?;
+        assert_eq!(right_listener.recv()?, 1);
+        let sum = engine.listen_with(add(left, right), REPLAY).wait();
+        assert_eq!(sum.recv()?, 41);
+        right_emitter.send(2)?;
+        assert_eq!(sum.recv()?, 42);
+        assert_eq!(right_listener.recv()?, 2);
+        left_emitter.send(0)?;
+        assert_eq!(left_listener.recv()?, 0);
+        assert_eq!(sum.recv()?, 2);
+    }
+
+    #[case]
+    pub fn add_joins_inputs_with_prestart_emissions() {
+        let left_emitter = engine.emit::<u64>(left_ref).wait();
+        let right_emitter = engine.emit::<u64>(right_ref).wait();
+        left_emitter.send(40)?;
+        right_emitter.send(2)?;
+        let sum = engine.listen(add(left, right)).wait();
+        engine.start().wait()?;
+        let first = sum.recv()?;
+        assert!(first == 40 || first == 2);
+        assert_eq!(sum.recv()?, 42);
+    }
+}
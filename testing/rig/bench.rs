@@ -0,0 +1,176 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    time::{Duration, Instant},
+};
+
+use engine_base::{
+    operators::{add, input, InputRef, Signal},
+    waiting::Waiting,
+    Emit, Engine,
+};
+
+const LATENCY_SAMPLES: u32 = 1_000;
+const THROUGHPUT_UPDATES: u32 = 10_000;
+
+const SHAPES: [Shape; 7] = [
+    Shape::Chain(1),
+    Shape::Chain(64),
+    Shape::Chain(512),
+    Shape::FanOut(16),
+    Shape::FanOut(256),
+    Shape::Diamond(8),
+    Shape::Diamond(32),
+];
+
+#[derive(Clone, Copy, Debug)]
+pub enum Shape {
+    // `add` nodes in a line, each reading the previous one and the input.
+    Chain(usize),
+    // This many `add` nodes reading the input, each with its own offset input and listener.
+    FanOut(usize),
+    // Diamonds stacked on each other, each splitting into two `add` nodes and joining them again.
+    Diamond(usize),
+}
+
+impl Shape {
+    // Returns the input, the offset inputs and the signals to listen to, one listener each. The
+    // offset input at a given position only feeds the signal at the same position.
+    fn build(self) -> (InputRef, Vec<InputRef>, Vec<Signal<u64>>) {
+        let (input_ref, source) = input::<u64>();
+        let mut offsets = Vec::new();
+        let signals = match self {
+            Shape::Chain(depth) => {
+                let mut chain = source.clone();
+                for _ in 0..depth {
+                    chain = add(chain, source.clone());
+                }
+                vec![chain]
+            }
+            Shape::FanOut(width) => (0..width)
+                .map(|_| {
+                    let (offset_ref, offset) = input::<u64>();
+                    offsets.push(offset_ref);
+                    add(source.clone(), offset)
+                })
+                .collect(),
+            Shape::Diamond(depth) => {
+                let mut diamond = source.clone();
+                for _ in 0..depth {
+                    let left = add(diamond.clone(), source.clone());
+                    let right = add(source.clone(), diamond);
+                    diamond = add(left, right);
+                }
+                vec![diamond]
+            }
+        };
+        (input_ref, offsets, signals)
+    }
+}
+
+impl Display for Shape {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Shape::Chain(depth) => write!(f, "chain({depth})"),
+            Shape::FanOut(width) => write!(f, "fan_out({width})"),
+            Shape::Diamond(depth) => write!(f, "diamond({depth})"),
+        }
+    }
+}
+
+// One result, printed as a single line of JSON.
+pub struct Measurement {
+    pub engine: String,
+    pub shape: Shape,
+    pub nodes: usize,
+    pub metric: &'static str,
+    pub value: f64,
+    pub unit: &'static str,
+}
+
+impl Display for Measurement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            r#"{{"engine":"{}","graph":"{}","nodes":{},"metric":"{}","value":{:.1},"unit":"{}"}}"#,
+            self.engine, self.shape, self.nodes, self.metric, self.value, self.unit
+        )
+    }
+}
+
+pub fn run<E: Engine>(engine: &str, constructor: impl Fn() -> E) {
+    for shape in SHAPES {
+        for measurement in measure(engine, constructor(), shape) {
+            println!("{measurement}");
+        }
+    }
+}
+
+pub fn measure<E: Engine>(name: &str, engine: E, shape: Shape) -> Vec<Measurement> {
+    let (input_ref, offsets, signals) = shape.build();
+    let started = Instant::now();
+    let listeners = signals
+        .into_iter()
+        .map(|signal| engine.listen(signal).wait())
+        .collect::<Vec<_>>();
+    let registration = started.elapsed();
+    let nodes = engine.describe().wait().nodes.len();
+    let emitter = engine.emit::<u64>(input_ref).wait();
+    engine.start().wait().expect("Engine failed to start");
+    for (value, (offset, listener)) in (0..).zip(offsets.into_iter().zip(&listeners)) {
+        engine
+            .emit::<u64>(offset)
+            .wait()
+            .send(value)
+            .expect("Engine stopped early");
+        listener.recv().expect("Engine stopped early");
+    }
+
+    // Every listener is notified before the next value is sent.
+    let mut latencies = (0..LATENCY_SAMPLES)
+        .map(|value| {
+            let started = Instant::now();
+            emitter
+                .send(u64::from(value))
+                .expect("Engine stopped early");
+            for listener in &listeners {
+                listener.recv().expect("Engine stopped early");
+            }
+            started.elapsed()
+        })
+        .collect::<Vec<_>>();
+    latencies.sort_unstable();
+
+    let started = Instant::now();
+    for value in 0..THROUGHPUT_UPDATES {
+        emitter
+            .send(u64::from(value))
+            .expect("Engine stopped early");
+    }
+    for listener in &listeners {
+        for _ in 0..THROUGHPUT_UPDATES {
+            listener.recv().expect("Engine stopped early");
+        }
+    }
+    let throughput = f64::from(THROUGHPUT_UPDATES) / started.elapsed().as_secs_f64();
+    engine.shutdown().wait();
+
+    let percentile = |percent: usize| nanos(latencies[latencies.len() * percent / 100]);
+    let measurement = |metric, value, unit| Measurement {
+        engine: name.to_string(),
+        shape,
+        nodes,
+        metric,
+        value,
+        unit,
+    };
+    vec![
+        measurement("registration", nanos(registration), "ns"),
+        measurement("latency_p50", percentile(50), "ns"),
+        measurement("latency_p99", percentile(99), "ns"),
+        measurement("throughput", throughput, "updates/s"),
+    ]
+}
+
+fn nanos(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1e9
+}
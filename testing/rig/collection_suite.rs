@@ -0,0 +1,158 @@
+use rig_macros::test_suite;
+
+#[test_suite]
+pub mod collection {
+
+    use engine_base::{
+        operators::{
+            collection::{Change, Map},
+            count, filter, group_by, input, input_named, map, sum,
+        },
+        waiting::Waiting,
+        Emit, Engine, ListenOptions,
+    };
+
+    const REPLAY: ListenOptions = ListenOptions {
+        replay_current: true,
+    };
+
+    #[setup]
+    fn setup<T: Engine>(e: T) {
+        let engine = e;
+        let (input_ref, signal) = input::<Map<u64, u64>>();
+    }
+
+    #[case]
+    pub fn listeners_receive_changes_only() {
+        let listener = engine.listen_with(signal, REPLAY).wait();
+        let emitter = engine.emit::<Map<u64, u64>>(input_ref).wait();
+        engine.start().wait()?;
+        emitter.send(Map::new().insert(1, 10).insert(2, 20))?;
+        emitter.send(Map::new().insert(1, 11).insert(2, 20).remove(2).remove(3))?;
+        assert_eq!(listener.recv()?.changes(), []);
+        assert_eq!(
+            listener.recv()?.into_changes(),
+            vec![Change::Insert(1, 10), Change::Insert(2, 20)]
+        );
+        // Rewriting a value it already holds or removing a key it lacks changes nothing.
+        assert_eq!(
+            listener.recv()?.into_changes(),
+            vec![Change::Insert(1, 11), Change::Remove(2)]
+        );
+    }
+
+    #[case]
+    pub fn replay_lists_every_entry() {
+        let probe = engine.listen(signal.clone()).wait();
+        let emitter = engine.emit::<Map<u64, u64>>(input_ref).wait();
+        engine.start().wait()?;
+        emitter.send(Map::new().insert(1, 10).insert(2, 20))?;
+        emitter.send(Map::new().remove(1).insert(3, 30))?;
+        probe.recv()?;
+        probe.recv()?;
+        let listener = engine.listen_with(signal, REPLAY).wait();
+        let mut replayed = listener.recv()?.into_changes();
+        replayed.sort_by_key(|change| match change {
+            Change::Insert(key, _) | Change::Remove(key) => *key,
+        });
+        assert_eq!(replayed, vec![Change::Insert(2, 20), Change::Insert(3, 30)]);
+    }
+
+    #[case]
+    pub fn filter_follows_values_in_and_out() {
+        let listener = engine
+            .listen(filter(signal, |value: &u64| value.is_multiple_of(2)))
+            .wait();
+        let emitter = engine.emit::<Map<u64, u64>>(input_ref).wait();
+        engine.start().wait()?;
+        emitter.send(Map::new().insert(1, 1).insert(2, 2))?;
+        emitter.send(Map::new().insert(1, 4).insert(2, 5))?;
+        emitter.send(Map::new().remove(1).insert(3, 3))?;
+        assert_eq!(listener.recv()?.into_changes(), vec![Change::Insert(2, 2)]);
+        assert_eq!(
+            listener.recv()?.into_changes(),
+            vec![Change::Insert(1, 4), Change::Remove(2)]
+        );
+        assert_eq!(listener.recv()?.into_changes(), vec![Change::Remove(1)]);
+    }
+
+    #[case]
+    pub fn map_transforms_values() {
+        let doubled = map(signal, |value: u64| value * 2);
+        let listener = engine.listen(doubled.clone()).wait();
+        let emitter = engine.emit::<Map<u64, u64>>(input_ref).wait();
+        engine.start().wait()?;
+        emitter.send(Map::new().insert(1, 1).insert(2, 2))?;
+        emitter.send(Map::new().remove(1))?;
+        assert_eq!(
+            listener.recv()?.into_changes(),
+            vec![Change::Insert(1, 2), Change::Insert(2, 4)]
+        );
+        assert_eq!(listener.recv()?.into_changes(), vec![Change::Remove(1)]);
+        let total = engine.listen_with(sum(doubled), REPLAY).wait();
+        assert_eq!(total.recv()?, 4);
+    }
+
+    #[case]
+    pub fn count_and_sum_track_entries() {
+        let counted = engine.listen(count(signal.clone())).wait();
+        let summed = engine.listen(sum(signal)).wait();
+        let emitter = engine.emit::<Map<u64, u64>>(input_ref).wait();
+        engine.start().wait()?;
+        emitter.send(Map::new().insert(1, 10).insert(2, 20))?;
+        emitter.send(Map::new().insert(1, 15))?;
+        emitter.send(Map::new().remove(2).remove(7))?;
+        assert_eq!(counted.recv()?, 2);
+        assert_eq!(counted.recv()?, 2);
+        assert_eq!(counted.recv()?, 1);
+        assert_eq!(summed.recv()?, 30);
+        assert_eq!(summed.recv()?, 35);
+        assert_eq!(summed.recv()?, 15);
+    }
+
+    #[case]
+    pub fn group_by_counts_entries_per_group() {
+        let listener = engine
+            .listen(group_by(signal, |value: &u64| value % 3))
+            .wait();
+        let emitter = engine.emit::<Map<u64, u64>>(input_ref).wait();
+        engine.start().wait()?;
+        emitter.send(Map::new().insert(1, 3).insert(2, 6).insert(3, 1))?;
+        emitter.send(Map::new().insert(3, 9))?;
+        emitter.send(Map::new().remove(1).remove(2).remove(3))?;
+        assert_eq!(
+            listener.recv()?.into_changes(),
+            vec![Change::Insert(0, 2), Change::Insert(1, 1)]
+        );
+        // Moving to another group leaves the old one, which is gone once it is empty.
+        assert_eq!(
+            listener.recv()?.into_changes(),
+            vec![Change::Remove(1), Change::Insert(0, 3)]
+        );
+        assert_eq!(listener.recv()?.into_changes(), vec![Change::Remove(0)]);
+    }
+
+    #[case]
+    pub fn entries_survive_snapshots() {
+        let (input_ref, signal) = input_named::<Map<u64, u64>>("entries_survive_snapshots")?;
+        let counted = count(signal.clone());
+        let probe = engine.listen(counted.clone()).wait();
+        let emitter = engine.emit::<Map<u64, u64>>(input_ref).wait();
+        engine.start().wait()?;
+        emitter.send(Map::new().insert(1, 10).insert(2, 20))?;
+        assert_eq!(probe.recv()?, 2);
+        let bytes = engine.snapshot().wait()?;
+        engine.shutdown().wait();
+
+        let restored = T::restore(&bytes)?;
+        let listener = restored.listen_with(counted, REPLAY).wait();
+        let summed = restored.listen_with(sum(signal), REPLAY).wait();
+        let emitter = restored.emit::<Map<u64, u64>>(input_ref).wait();
+        restored.start().wait()?;
+        emitter.send(Map::new().remove(1))?;
+        assert_eq!(listener.recv()?, 2);
+        assert_eq!(listener.recv()?, 1);
+        assert_eq!(summed.recv()?, 30);
+        assert_eq!(summed.recv()?, 20);
+    }
+}
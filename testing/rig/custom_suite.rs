@@ -0,0 +1,100 @@
+use engine_macros::RType;
+use rig_macros::test_suite;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, RType)]
+#[rtype(name = "rig.Reading")]
+pub struct Reading {
+    pub sensor: String,
+    pub celsius: i64,
+}
+
+#[test_suite]
+pub mod custom {
+
+    use engine_base::{
+        describe::NodeKind,
+        operators::{changes, input, input_named, types::Type},
+        snapshot::SnapshotError,
+        waiting::Waiting,
+        Emit, Engine, ListenOptions,
+    };
+
+    use super::Reading;
+
+    const REPLAY: ListenOptions = ListenOptions {
+        replay_current: true,
+    };
+
+    #[setup]
+    fn setup<T: Engine>(e: T) {
+        let engine = e;
+        let (input_ref, signal) = input::<Reading>();
+        let reading = Reading {
+            sensor: "boiler".to_string(),
+            celsius: 71,
+        };
+    }
+
+    #[case]
+    pub fn custom_values_reach_listeners() {
+        let listener = engine.listen(signal).wait();
+        let emitter = engine.emit::<Reading>(input_ref).wait();
+        engine.start().wait()?;
+        emitter.send(reading.clone())?;
+        assert_eq!(listener.recv()?, reading);
+    }
+
+    #[case]
+    pub fn custom_inputs_start_at_default() {
+        let listener = engine.listen_with(signal, REPLAY).wait();
+        assert_eq!(listener.recv()?, Reading::default());
+        let emitter = engine.emit::<Reading>(input_ref).wait();
+        engine.start().wait()?;
+        emitter.send(reading.clone())?;
+        assert_eq!(listener.recv()?, reading);
+    }
+
+    #[case]
+    pub fn custom_types_are_described_by_name() {
+        let listener = engine.listen(signal).wait();
+        let emitter = engine.emit::<Reading>(input_ref).wait();
+        engine.start().wait()?;
+        emitter.send(reading)?;
+        listener.recv()?;
+        let description = engine.describe().wait();
+        let node = &description.nodes[0];
+        assert!(matches!(node.kind, NodeKind::Input(_)));
+        let Type::Custom(custom) = node.rtype else {
+            anyhow::bail!("expected a custom type, got {:?}", node.rtype);
+        };
+        assert_eq!(custom.name(), "rig.Reading");
+    }
+
+    #[case]
+    pub fn custom_changes_skip_equal_values() {
+        let probe = engine.listen(signal.clone()).wait();
+        let listener = engine.listen_event(changes(signal)).wait();
+        let emitter = engine.emit::<Reading>(input_ref).wait();
+        engine.start().wait()?;
+        for _ in 0..2 {
+            emitter.send(reading.clone())?;
+            assert_eq!(probe.recv()?, reading);
+        }
+        assert_eq!(listener.recv()?, reading);
+        assert!(listener.try_recv().is_err());
+    }
+
+    #[case]
+    pub fn custom_inputs_are_not_persisted() {
+        let (input_ref, signal) = input_named::<Reading>("custom_inputs_are_not_persisted")?;
+        let probe = engine.listen(signal).wait();
+        let emitter = engine.emit::<Reading>(input_ref).wait();
+        engine.start().wait()?;
+        emitter.send(reading)?;
+        probe.recv()?;
+        assert!(matches!(
+            engine.snapshot().wait(),
+            Err(SnapshotError::Unpersistable(_))
+        ));
+    }
+}
@@ -0,0 +1,88 @@
+use rig_macros::test_suite;
+
+#[test_suite]
+pub mod describe {
+
+    use engine_base::{
+        describe::NodeKind,
+        operators::{add, input, input_named, types::Wrapper},
+        waiting::Waiting,
+        Emit, Engine,
+    };
+
+    #[setup]
+    fn setup<T: Engine>(e: T) {
+        let engine = e;
+        let (left_ref, left) = input::<u64>();
+        let (right_ref, right) = input::<u64>();
+    }
+
+    #[case]
+    pub fn describe_lists_nodes_and_edges() {
+        let _listener = engine.listen(add(left, right)).wait();
+        let _emitter = engine.emit::<u64>(left_ref).wait();
+        let description = engine.describe().wait();
+        assert_eq!(description.nodes.len(), 3);
+        assert_eq!(description.nodes[0].kind, NodeKind::Input(left_ref));
+        assert_eq!(description.nodes[0].emitters, 1);
+        assert_eq!(description.nodes[1].kind, NodeKind::Input(right_ref));
+        assert_eq!(description.nodes[1].emitters, 0);
+        assert_eq!(description.nodes[2].kind, NodeKind::Add);
+        assert_eq!(description.nodes[2].listeners, 1);
+        assert_eq!(description.edges().collect::<Vec<_>>(), [(0, 2), (1, 2)]);
+    }
+
+    #[case]
+    pub fn describe_reports_current_values() {
+        let listener = engine.listen(add(left, right)).wait();
+        let left_emitter = engine.emit::<u64>(left_ref).wait();
+        let right_emitter = engine.emit::<u64>(right_ref).wait();
+        engine.start().wait()?;
+        left_emitter.send(40)?;
+        right_emitter.send(2)?;
+        assert_eq!(listener.recv()?, 40);
+        assert_eq!(listener.recv()?, 42);
+        let description = engine.describe().wait();
+        let values = description
+            .nodes
+            .iter()
+            .map(|node| node.value.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            [Wrapper::U64(40), Wrapper::U64(2), Wrapper::U64(42)]
+        );
+    }
+
+    #[case]
+    pub fn describe_exports_dot_and_json() {
+        let _listener = engine.listen(add(left, right)).wait();
+        let _emitter = engine.emit::<u64>(left_ref).wait();
+        let _other_emitter = engine.emit::<u64>(right_ref).wait();
+        let description = engine.describe().wait();
+
+        let dot = description.to_dot();
+        assert!(dot.starts_with("digraph engine {"));
+        assert!(dot.contains("n0 -> n2;"));
+        assert!(dot.contains("n1 -> n2;"));
+
+        let json = description.to_json();
+        assert!(json.contains(
+            "{\"id\":2,\"kind\":\"add\",\"type\":\"U64\",\"dependencies\":[0,1],\"listeners\":1,\"emitters\":0,\"value\":0}"
+        ));
+    }
+
+    #[case]
+    pub fn signals_display_as_expressions() {
+        let (_, named_left) = input_named::<u64>("signals_display_as_expressions__left")?;
+        let (_, named_right) = input_named::<u64>("signals_display_as_expressions__right")?;
+        assert_eq!(
+            format!("{:?}", add(named_left, named_right)),
+            "Signal(add(input(\"signals_display_as_expressions__left\"), input(\"signals_display_as_expressions__right\")): U64)"
+        );
+        let _listener = engine.listen(add(left, right)).wait();
+        let _emitter = engine.emit::<u64>(left_ref).wait();
+        let _other_emitter = engine.emit::<u64>(right_ref).wait();
+        engine.shutdown().wait();
+    }
+}
@@ -0,0 +1,186 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    ops::Range,
+    time::Duration,
+};
+
+use anyhow::bail;
+use crossbeam_channel::Receiver;
+use engine_base::{waiting::Waiting, Emit, Engine, ListenOptions};
+
+use crate::script::{Rng, Script, Step};
+
+const STALL_TIMEOUT: Duration = Duration::from_secs(5);
+
+// The values each listener of a script received, in the order of its listen steps.
+pub type Observed = Vec<Vec<u64>>;
+
+// Runs the same scripts against a reference engine and a candidate engine, and fails with a
+// minimized script when listeners observe different values.
+pub struct Differential<A, B> {
+    reference: fn() -> A,
+    candidate: fn() -> B,
+}
+
+impl<A: Engine, B: Engine> Differential<A, B> {
+    pub fn new(reference: fn() -> A, candidate: fn() -> B) -> Self {
+        Self {
+            reference,
+            candidate,
+        }
+    }
+
+    pub fn check_random(&self, seeds: Range<u64>, inputs: usize, steps: usize) -> runner::Result {
+        for seed in seeds {
+            let script = Script::random(&mut Rng::new(seed), inputs, steps);
+            if let Some(divergence) = self.diverges(&script) {
+                let divergence = self.minimize(script, divergence);
+                bail!("seed {seed} diverged\n{divergence}");
+            }
+        }
+        Ok(())
+    }
+
+    pub fn diverges(&self, script: &Script) -> Option<Divergence> {
+        let reference = run((self.reference)(), script);
+        let candidate = run((self.candidate)(), script);
+        if reference.is_ok() && reference == candidate {
+            return None;
+        }
+        Some(Divergence {
+            script: script.clone(),
+            reference,
+            candidate,
+        })
+    }
+
+    // Greedily drops chunks of steps, halving the chunk size whenever nothing can be dropped.
+    // Smaller scripts only replace the divergence when they fail the same way.
+    pub fn minimize(&self, script: Script, divergence: Divergence) -> Divergence {
+        let failure = divergence.failure();
+        let mut best = divergence;
+        let mut chunk = script.steps.len().div_ceil(2);
+        while chunk > 0 {
+            let mut start = 0;
+            let mut shrunk = false;
+            while start < best.script.steps.len() {
+                let smaller = best.script.without(start, chunk);
+                let divergence = self
+                    .diverges(&smaller)
+                    .filter(|divergence| divergence.failure() == failure);
+                if let Some(divergence) = divergence {
+                    best = divergence;
+                    shrunk = true;
+                } else {
+                    start += chunk;
+                }
+            }
+            if !shrunk {
+                chunk /= 2;
+            }
+        }
+        best
+    }
+}
+
+pub struct Divergence {
+    pub script: Script,
+    pub reference: Result<Observed, String>,
+    pub candidate: Result<Observed, String>,
+}
+
+// How a script diverged.
+#[derive(PartialEq, Eq)]
+enum Failure {
+    // The listen step of the first listener that received different values.
+    Mismatch(Step),
+    // Which of the engines failed to run the script.
+    Failed { reference: bool, candidate: bool },
+}
+
+impl Divergence {
+    fn failure(&self) -> Failure {
+        let (Ok(reference), Ok(candidate)) = (&self.reference, &self.candidate) else {
+            return Failure::Failed {
+                reference: self.reference.is_err(),
+                candidate: self.candidate.is_err(),
+            };
+        };
+        let listener = (0..reference.len().max(candidate.len()))
+            .find(|&listener| reference.get(listener) != candidate.get(listener))
+            .expect("Diverging listeners received different values");
+        let step = self
+            .script
+            .steps
+            .iter()
+            .filter(|step| matches!(step, Step::Listen { .. }))
+            .nth(listener)
+            .expect("Every listener has a listen step");
+        Failure::Mismatch(step.clone())
+    }
+}
+
+impl Display for Divergence {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.script)?;
+        match (&self.reference, &self.candidate) {
+            (Ok(reference), Ok(candidate)) => {
+                let listeners = reference.len().max(candidate.len());
+                for listener in 0..listeners {
+                    let expected = reference.get(listener);
+                    let actual = candidate.get(listener);
+                    if expected != actual {
+                        writeln!(f, "listener {listener}:")?;
+                        writeln!(f, "  reference: {expected:?}")?;
+                        writeln!(f, "  candidate: {actual:?}")?;
+                    }
+                }
+                Ok(())
+            }
+            (reference, candidate) => {
+                writeln!(f, "reference: {reference:?}")?;
+                writeln!(f, "candidate: {candidate:?}")
+            }
+        }
+    }
+}
+
+// Every emit waits until the engine has seen the value, so updates to different inputs cannot be
+// reordered and each listener's sequence is deterministic.
+pub fn run<E: Engine>(engine: E, script: &Script) -> Result<Observed, String> {
+    let (inputs, signals) = script.inputs();
+    let mut emitters = (0..script.inputs).map(|_| None).collect::<Vec<_>>();
+    let mut listeners = Vec::<Receiver<u64>>::new();
+    engine.start().wait().map_err(|error| error.to_string())?;
+    for (id, step) in script.steps.iter().enumerate() {
+        match step {
+            Step::Listen { signal, replay } => {
+                let options = ListenOptions {
+                    replay_current: *replay,
+                };
+                listeners.push(engine.listen_with(signal.build(&signals), options).wait());
+            }
+            Step::Emit { input, value } => {
+                let (emitter, probe) = emitters[*input].get_or_insert_with(|| {
+                    let probe = engine.listen(signals[*input].clone()).wait();
+                    (engine.emit::<u64>(inputs[*input]).wait(), probe)
+                });
+                emitter
+                    .send(*value)
+                    .map_err(|_| format!("step {id}: emitter disconnected"))?;
+                match probe.recv_timeout(STALL_TIMEOUT) {
+                    Ok(received) if received == *value => {}
+                    Ok(received) => {
+                        return Err(format!("step {id}: input received {received}"));
+                    }
+                    Err(_) => return Err(format!("step {id}: engine stalled")),
+                }
+            }
+        }
+    }
+    engine.shutdown().wait();
+    Ok(listeners
+        .iter()
+        .map(|listener| listener.try_iter().collect())
+        .collect())
+}
@@ -0,0 +1,28 @@
+use rig_macros::test_suite;
+
+#[test_suite]
+pub mod differential {
+    use engine_base::Engine;
+
+    use crate::differential::Differential;
+
+    #[setup]
+    fn setup<A: Engine, B: Engine>(e: Differential<A, B>) {
+        let pair = e;
+    }
+
+    #[case]
+    pub fn single_input_scripts_agree() {
+        pair.check_random(0..32, 1, 20)?;
+    }
+
+    #[case]
+    pub fn random_scripts_agree() {
+        pair.check_random(100..164, 4, 40)?;
+    }
+
+    #[case]
+    pub fn long_scripts_agree() {
+        pair.check_random(1000..1008, 8, 200)?;
+    }
+}
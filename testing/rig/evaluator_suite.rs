@@ -0,0 +1,84 @@
+use rig_macros::test_suite;
+
+// Expects a compiled engine reporting to the metrics it is paired with.
+#[test_suite]
+pub mod evaluator {
+
+    use std::sync::Arc;
+
+    use engine_base::{
+        observer::Metrics,
+        operators::{add, changes, event, hold, input, snapshot, switch, Signal},
+        waiting::Waiting,
+        Emit, Engine,
+    };
+
+    #[setup]
+    fn setup<T: Engine>(e: (T, Arc<Metrics>)) {
+        let (engine, metrics) = e;
+        let (input_ref, signal) = input::<u64>();
+    }
+
+    #[case]
+    pub fn events_run_compiled() {
+        let (clicks_ref, clicks) = event::<u64>();
+        let distinct = changes(hold(clicks.clone(), 0));
+        let listener = engine.listen_event(distinct.clone()).wait();
+        let sampled = engine.listen_event(snapshot(distinct, signal)).wait();
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        let clicks_emitter = engine.emit::<u64>(clicks_ref).wait();
+        engine.start().wait()?;
+        emitter.send(7)?;
+        for value in [1, 1, 2] {
+            clicks_emitter.send(value)?;
+        }
+        assert_eq!(listener.recv()?, 1);
+        assert_eq!(sampled.recv()?, 7);
+        assert_eq!(listener.recv()?, 2);
+        assert_eq!(sampled.recv()?, 7);
+        assert!(listener.try_recv().is_err());
+        assert!(sampled.try_recv().is_err());
+        engine.describe().wait();
+        let report = metrics.report();
+        assert_eq!(report.updates, 4);
+        assert_eq!(report.compiled_updates, 4);
+    }
+
+    #[case]
+    pub fn switches_run_compiled() {
+        let (selector_ref, selector) = input::<Signal<u64>>();
+        let listener = engine.listen(switch(selector)).wait();
+        let probe = engine.listen(signal.clone()).wait();
+        let selector_emitter = engine.emit::<Signal<u64>>(selector_ref).wait();
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        engine.start().wait()?;
+        emitter.send(1)?;
+        probe.recv()?;
+        selector_emitter.send(signal)?;
+        assert_eq!(listener.recv()?, 1);
+        engine.describe().wait();
+        let report = metrics.report();
+        assert_eq!(report.updates, 2);
+        assert_eq!(report.compiled_updates, 2);
+    }
+
+    #[case]
+    pub fn switches_follow_signals_registered_after_them() {
+        let (selector_ref, selector) = input::<Signal<u64>>();
+        let listener = engine.listen(add(switch(selector), signal.clone())).wait();
+        let selector_emitter = engine.emit::<Signal<u64>>(selector_ref).wait();
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        engine.start().wait()?;
+        selector_emitter.send(add(signal.clone(), signal))?;
+        assert_eq!(listener.recv()?, 0);
+        emitter.send(1)?;
+        assert_eq!(listener.recv()?, 3);
+        emitter.send(2)?;
+        assert_eq!(listener.recv()?, 6);
+        assert!(listener.try_recv().is_err());
+        engine.describe().wait();
+        let report = metrics.report();
+        assert_eq!(report.updates, 3);
+        assert_eq!(report.compiled_updates, 3);
+    }
+}
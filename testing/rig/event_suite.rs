@@ -0,0 +1,118 @@
+use rig_macros::test_suite;
+
+#[test_suite]
+pub mod events {
+
+    use engine_base::{
+        operators::{changes, event, event_named, hold, input, snapshot},
+        waiting::Waiting,
+        Emit, Engine, ListenOptions,
+    };
+
+    const REPLAY: ListenOptions = ListenOptions {
+        replay_current: true,
+    };
+
+    #[setup]
+    fn setup<T: Engine>(e: T) {
+        let engine = e;
+        let (clicks_ref, clicks) = event::<u64>();
+    }
+
+    #[case]
+    pub fn event_listeners_only_hear_later_occurrences() {
+        let held = engine.listen_with(hold(clicks.clone(), 0), REPLAY).wait();
+        let emitter = engine.emit::<u64>(clicks_ref).wait();
+        engine.start().wait()?;
+        assert_eq!(held.recv()?, 0);
+        emitter.send(5)?;
+        assert_eq!(held.recv()?, 5);
+        let listener = engine.listen_event(clicks).wait();
+        emitter.send(6)?;
+        assert_eq!(held.recv()?, 6);
+        assert_eq!(listener.recv()?, 6);
+        assert!(listener.try_recv().is_err());
+    }
+
+    #[case]
+    pub fn hold_starts_at_init_rather_than_zero() {
+        let listener = engine.listen_with(hold(clicks, 7), REPLAY).wait();
+        let emitter = engine.emit::<u64>(clicks_ref).wait();
+        engine.start().wait()?;
+        emitter.send(3)?;
+        emitter.send(3)?;
+        assert_eq!(listener.recv()?, 7);
+        assert_eq!(listener.recv()?, 3);
+        // A hold is a signal, so it hears about every occurrence, even ones that change nothing.
+        assert_eq!(listener.recv()?, 3);
+    }
+
+    #[case]
+    pub fn changes_skip_repeated_values() {
+        let held = hold(clicks, 0);
+        let probe = engine.listen(held.clone()).wait();
+        let distinct = changes(held);
+        let listener = engine.listen_event(distinct.clone()).wait();
+        let counted = engine.listen(hold(distinct, 0)).wait();
+        let emitter = engine.emit::<u64>(clicks_ref).wait();
+        engine.start().wait()?;
+        for value in [1, 1, 2, 2, 1] {
+            emitter.send(value)?;
+            assert_eq!(probe.recv()?, value);
+        }
+        assert_eq!(listener.recv()?, 1);
+        assert_eq!(listener.recv()?, 2);
+        assert_eq!(listener.recv()?, 1);
+        assert!(listener.try_recv().is_err());
+        assert_eq!(counted.recv()?, 1);
+        assert_eq!(counted.recv()?, 2);
+        assert_eq!(counted.recv()?, 1);
+        assert!(counted.try_recv().is_err());
+    }
+
+    #[case]
+    pub fn snapshot_samples_only_when_the_event_occurs() {
+        let (level_ref, level) = input::<u64>();
+        let probe = engine.listen(level.clone()).wait();
+        let listener = engine.listen_event(snapshot(clicks, level)).wait();
+        let clicks_emitter = engine.emit::<u64>(clicks_ref).wait();
+        let level_emitter = engine.emit::<u64>(level_ref).wait();
+        engine.start().wait()?;
+        level_emitter.send(4)?;
+        assert_eq!(probe.recv()?, 4);
+        clicks_emitter.send(1)?;
+        assert_eq!(listener.recv()?, 4);
+        level_emitter.send(9)?;
+        level_emitter.send(10)?;
+        assert_eq!(probe.recv()?, 9);
+        assert_eq!(probe.recv()?, 10);
+        assert!(listener.try_recv().is_err());
+        clicks_emitter.send(1)?;
+        assert_eq!(listener.recv()?, 10);
+    }
+
+    #[case]
+    pub fn holds_survive_snapshots() {
+        let (clicks_ref, clicks) = event_named::<u64>("holds_survive_snapshots")?;
+        let held = hold(clicks.clone(), 1);
+        let probe = engine.listen(held.clone()).wait();
+        let emitter = engine.emit::<u64>(clicks_ref).wait();
+        engine.start().wait()?;
+        emitter.send(3)?;
+        assert_eq!(probe.recv()?, 3);
+        let bytes = engine.snapshot().wait()?;
+        engine.shutdown().wait();
+
+        let restored = T::restore(&bytes)?;
+        let listener = restored.listen_with(held, REPLAY).wait();
+        let distinct = restored.listen_event(changes(hold(clicks, 1))).wait();
+        let emitter = restored.emit::<u64>(clicks_ref).wait();
+        restored.start().wait()?;
+        emitter.send(3)?;
+        emitter.send(4)?;
+        assert_eq!(listener.recv()?, 3);
+        assert_eq!(listener.recv()?, 3);
+        assert_eq!(listener.recv()?, 4);
+        assert_eq!(distinct.recv()?, 4);
+    }
+}
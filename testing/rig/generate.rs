@@ -0,0 +1,77 @@
+use std::ops::Range;
+
+use anyhow::bail;
+use engine_base::Engine;
+use runner::model::Test;
+
+use crate::{
+    differential::{run, Divergence},
+    oracle,
+    script::{Rng, Script, SignalSpec, Step},
+};
+
+// A random DAG over `inputs` inputs. Every node adds two earlier signals, so later nodes share
+// the subexpressions of earlier ones. Nodes are in dependency order, inputs first.
+pub fn random_graph(rng: &mut Rng, inputs: usize, nodes: usize) -> Vec<SignalSpec> {
+    let mut graph = (0..inputs).map(SignalSpec::Input).collect::<Vec<_>>();
+    for _ in 0..nodes {
+        let left = graph[rng.below(graph.len())].clone();
+        let right = graph[rng.below(graph.len())].clone();
+        graph.push(SignalSpec::Add(Box::new(left), Box::new(right)));
+    }
+    graph
+}
+
+// Listens to a few nodes of a random graph up front, then mixes emissions with further listens.
+// Graphs stay small enough that sums of values below 1000 never overflow.
+pub fn random_script(seed: u64) -> Script {
+    let mut rng = Rng::new(seed);
+    let inputs = 1 + rng.below(4);
+    let nodes = 1 + rng.below(12);
+    let graph = random_graph(&mut rng, inputs, nodes);
+    let listen = |rng: &mut Rng| Step::Listen {
+        signal: graph[rng.below(graph.len())].clone(),
+        replay: rng.chance(50),
+    };
+    let mut steps = (0..=rng.below(3))
+        .map(|_| listen(&mut rng))
+        .collect::<Vec<_>>();
+    for _ in 0..30 {
+        if rng.chance(15) {
+            steps.push(listen(&mut rng));
+        } else {
+            steps.push(Step::Emit {
+                input: rng.below(inputs),
+                value: rng.below(1000) as u64,
+            });
+        }
+    }
+    Script { inputs, steps }
+}
+
+pub fn check<E: Engine>(engine: E, seed: u64) -> runner::Result {
+    let script = random_script(seed);
+    let expected = oracle::expected(&script);
+    let observed = run(engine, &script);
+    if observed.as_ref() != Ok(&expected) {
+        let divergence = Divergence {
+            script,
+            reference: Ok(expected),
+            candidate: observed,
+        };
+        bail!("seed {seed} disagrees with the oracle\n{divergence}");
+    }
+    Ok(())
+}
+
+pub fn suite<T: Engine>(seeds: Range<u64>) -> Test<T> {
+    Test::Suite {
+        name: "generated".to_string(),
+        tests: seeds
+            .map(|seed| Test::Case {
+                name: format!("seed_{seed}"),
+                code: Box::new(move |engine| check(engine, seed)),
+            })
+            .collect(),
+    }
+}
@@ -6,7 +6,7 @@ pub mod input {
     use engine_base::{
         operators::input,
         waiting::{MaybeWaiting, Waiting},
-        Engine,
+        Emit, Engine,
     };
 
     #[setup]
@@ -17,7 +17,7 @@ pub mod input {
 
     #[case]
     pub fn input_forwards_signal__already_running__register_on_running() {
-        engine.start().wait();
+        engine.start().wait()?;
         let emitter = engine.emit::<u64>(input_ref).wait();
         let listener = engine.listen(signal).wait();
         emitter.send(42)?;
@@ -29,14 +29,14 @@ pub mod input {
         let emitter = engine.emit::<u64>(input_ref).wait();
         let listener = engine.listen(signal).wait();
         emitter.send(42)?;
-        engine.start().immediate();
+        engine.start().immediate()?;
         assert_eq!(listener.recv()?, 42);
     }
 
     #[case]
     pub fn input_forwards_signal__start_after_emitter_register() {
         let emitter = engine.emit::<u64>(input_ref).wait();
-        engine.start().wait();
+        engine.start().wait()?;
         let listener = engine.listen(signal).wait();
         emitter.send(42)?;
         assert_eq!(listener.recv()?, 42);
@@ -44,7 +44,7 @@ pub mod input {
 
     #[case]
     pub fn input_forwards_signal__reversed__already_running() {
-        engine.start().wait();
+        engine.start().wait()?;
         let listener = engine.listen(signal).wait();
         let emitter = engine.emit::<u64>(input_ref).wait();
         emitter.send(42)?;
@@ -56,16 +56,30 @@ pub mod input {
         let listener = engine.listen(signal).wait();
         let emitter = engine.emit::<u64>(input_ref).wait();
         emitter.send(42)?;
-        engine.start().immediate();
+        engine.start().immediate()?;
         assert_eq!(listener.recv()?, 42);
     }
 
     #[case]
     pub fn input_forwards_signal__reversed__start_after_emitter_register() {
         let listener = engine.listen(signal).wait();
-        engine.start().wait();
+        engine.start().wait()?;
         let emitter = engine.emit::<u64>(input_ref).wait();
         emitter.send(42)?;
         assert_eq!(listener.recv()?, 42);
     }
+
+    #[case]
+    pub fn input_forwards_signal__two_listeners_on_one_node() {
+        let first = engine.listen(signal.clone()).wait();
+        let second = engine.listen(signal).wait();
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        engine.start().wait()?;
+        emitter.send(42)?;
+        assert_eq!(first.recv()?, 42);
+        assert_eq!(second.recv()?, 42);
+        drop(first);
+        emitter.send(7)?;
+        assert_eq!(second.recv()?, 7);
+    }
 }
@@ -1,17 +1,92 @@
 #![allow(unused_mut)]
+#![allow(unused_variables)]
 #![allow(non_snake_case)]
 #![allow(clippy::module_name_repetitions)]
 #![allow(clippy::missing_errors_doc)]
 
-use engine_base::Engine;
+use std::sync::Arc;
+
+use differential::Differential;
+use engine_base::{clock::ManualClock, observer::Metrics, Engine};
 use runner::model::Test;
 
+pub mod add_suite;
+pub mod bench;
+pub mod collection_suite;
+pub mod custom_suite;
+pub mod describe_suite;
+pub mod differential;
+pub mod differential_suite;
+pub mod evaluator_suite;
+pub mod event_suite;
+pub mod generate;
 pub mod input_suite;
+pub mod lifecycle_suite;
+pub mod missing_suite;
+pub mod named_suite;
+pub mod observed_suite;
+pub mod optional_suite;
+pub mod oracle;
+pub mod overflow_suite;
+pub mod pause_suite;
+pub mod queue_suite;
+pub mod replay_suite;
+pub mod result_suite;
 pub mod sanity_suite;
+pub mod script;
+pub mod shared_suite;
+pub mod shutdown_suite;
+pub mod snapshot_suite;
+pub mod switch_suite;
+pub mod window_suite;
 
 pub fn engine_suite<T: Engine>() -> Test<T> {
     Test::Suite {
         name: "Engine tests".to_string(),
-        tests: vec![sanity_suite::sanity::suite(), input_suite::input::suite()],
+        tests: vec![
+            sanity_suite::sanity::suite(),
+            lifecycle_suite::lifecycle::suite(),
+            pause_suite::pause::suite(),
+            shutdown_suite::shutdown::suite(),
+            input_suite::input::suite(),
+            shared_suite::shared::suite(),
+            custom_suite::custom::suite(),
+            optional_suite::optional::suite(),
+            result_suite::result::suite(),
+            switch_suite::switch::suite(),
+            collection_suite::collection::suite(),
+            event_suite::events::suite(),
+            replay_suite::replay::suite(),
+            named_suite::named::suite(),
+            add_suite::add::suite(),
+            overflow_suite::overflow::suite(),
+            describe_suite::describe::suite(),
+            snapshot_suite::snapshot::suite(),
+            generate::suite(0..32),
+        ],
     }
 }
+
+pub fn observed_suite<T: Engine>() -> Test<(T, Arc<Metrics>)> {
+    observed_suite::observed::suite()
+}
+
+pub fn evaluator_suite<T: Engine>() -> Test<(T, Arc<Metrics>)> {
+    evaluator_suite::evaluator::suite()
+}
+
+pub fn missing_suite<T: Engine>() -> Test<T> {
+    missing_suite::missing::suite()
+}
+
+pub fn queue_suite<T: Engine>() -> Test<(T, Arc<Metrics>)> {
+    queue_suite::queues::suite()
+}
+
+pub fn window_suite<T: Engine>() -> Test<(T, Arc<ManualClock>)> {
+    window_suite::windows::suite()
+}
+
+pub fn differential_suite<A: Engine, B: Engine>() -> Test<Differential<A, B>> {
+    differential_suite::differential::suite()
+}
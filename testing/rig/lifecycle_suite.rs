@@ -0,0 +1,41 @@
+use rig_macros::test_suite;
+
+#[test_suite]
+pub mod lifecycle {
+    use engine_base::{
+        lifecycle::{Lifecycle, LifecycleError},
+        waiting::{MaybeWaiting, Waiting},
+        Engine,
+    };
+
+    #[setup]
+    fn setup<T: Engine>(e: T) {
+        let engine = e;
+    }
+
+    #[case]
+    fn engine_starts_in_created_state() {
+        assert_eq!(engine.lifecycle(), Lifecycle::Created);
+        engine.start().wait()?;
+        assert_eq!(engine.lifecycle(), Lifecycle::Running);
+    }
+
+    #[case]
+    fn second_start_is_rejected() {
+        engine.start().wait()?;
+        assert_eq!(
+            engine.start().wait(),
+            Err(LifecycleError {
+                from: Lifecycle::Running,
+                to: Lifecycle::Running,
+            })
+        );
+    }
+
+    #[case]
+    fn second_start_is_rejected_without_waiting() {
+        engine.start().immediate()?;
+        assert!(engine.start().immediate().is_err());
+        engine.shutdown().wait();
+    }
+}
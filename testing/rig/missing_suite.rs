@@ -0,0 +1,71 @@
+use rig_macros::test_suite;
+
+// Engines configured to start inputs without a value instead of at zero.
+#[test_suite]
+pub mod missing {
+
+    use engine_base::{
+        operators::{add, input, optional, unwrap_or},
+        waiting::Waiting,
+        Emit, Engine, ListenOptions,
+    };
+
+    const REPLAY: ListenOptions = ListenOptions {
+        replay_current: true,
+    };
+
+    #[setup]
+    fn setup<T: Engine>(e: T) {
+        let engine = e;
+        let (a_ref, a) = input::<u64>();
+        let (b_ref, b) = input::<u64>();
+    }
+
+    #[case]
+    pub fn sums_stay_missing_until_every_input_is_set() {
+        let listener = engine
+            .listen_with(optional(add(a.clone(), b.clone())), REPLAY)
+            .wait();
+        let a_probe = engine.listen(a).wait();
+        let b_probe = engine.listen(b).wait();
+        let a_emitter = engine.emit::<u64>(a_ref).wait();
+        let b_emitter = engine.emit::<u64>(b_ref).wait();
+        engine.start().wait()?;
+        assert_eq!(listener.recv()?, None);
+        a_emitter.send(1)?;
+        a_probe.recv()?;
+        assert_eq!(listener.recv()?, None);
+        b_emitter.send(2)?;
+        b_probe.recv()?;
+        assert_eq!(listener.recv()?, Some(3));
+    }
+
+    #[case]
+    pub fn plain_listeners_skip_missing_values() {
+        let listener = engine.listen_with(add(a.clone(), b), REPLAY).wait();
+        let probe = engine.listen(a).wait();
+        let a_emitter = engine.emit::<u64>(a_ref).wait();
+        let b_emitter = engine.emit::<u64>(b_ref).wait();
+        engine.start().wait()?;
+        a_emitter.send(1)?;
+        probe.recv()?;
+        b_emitter.send(2)?;
+        assert_eq!(listener.recv()?, 3);
+    }
+
+    #[case]
+    pub fn unwrap_or_replaces_missing_inputs() {
+        let listener = engine
+            .listen_with(add(unwrap_or(optional(a), 5), b.clone()), REPLAY)
+            .wait();
+        let probe = engine.listen(unwrap_or(optional(b), 0)).wait();
+        let a_emitter = engine.emit::<u64>(a_ref).wait();
+        let b_emitter = engine.emit::<u64>(b_ref).wait();
+        engine.start().wait()?;
+        b_emitter.send(1)?;
+        probe.recv()?;
+        a_emitter.send(2)?;
+        assert_eq!(listener.recv()?, 6);
+        assert_eq!(listener.recv()?, 3);
+    }
+}
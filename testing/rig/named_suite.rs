@@ -0,0 +1,100 @@
+use rig_macros::test_suite;
+
+#[test_suite]
+pub mod named {
+
+    use engine_base::{
+        operators::{add, input_named, InputRef, Signal},
+        waiting::{MaybeWaiting, Waiting},
+        Emit, Engine, ListenOptions,
+    };
+
+    const REPLAY: ListenOptions = ListenOptions {
+        replay_current: true,
+    };
+
+    #[setup]
+    fn setup<T: Engine>(e: T) {
+        let engine = e;
+    }
+
+    #[case]
+    pub fn named_input_forwards_signal() {
+        let (input_ref, signal) = input_named::<u64>("named_input_forwards_signal")?;
+        let listener = engine.listen(signal).wait();
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        engine.start().immediate()?;
+        emitter.send(42)?;
+        assert_eq!(listener.recv()?, 42);
+    }
+
+    #[case]
+    pub fn input_names_are_unique() {
+        let (input_ref, _) = input_named::<u64>("input_names_are_unique")?;
+        assert!(input_named::<u64>("input_names_are_unique").is_err());
+        assert_eq!(InputRef::named("input_names_are_unique"), Some(input_ref));
+        assert_eq!(InputRef::named("input_names_are_unique__missing"), None);
+        engine.shutdown().wait();
+    }
+
+    #[case]
+    pub fn debug_shows_input_name() {
+        let (input_ref, _) = input_named::<u64>("debug_shows_input_name")?;
+        assert_eq!(
+            format!("{input_ref:?}"),
+            "InputRef(\"debug_shows_input_name\")"
+        );
+        engine.shutdown().wait();
+    }
+
+    #[case]
+    pub fn restored_engine_resolves_inputs_by_name() {
+        let (input_ref, signal) = input_named::<u64>("restored_engine_resolves_inputs_by_name")?;
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        let listener = engine.listen(signal.clone()).wait();
+        engine.start().wait()?;
+        emitter.send(42)?;
+        assert_eq!(listener.recv()?, 42);
+        let bytes = engine.snapshot().wait()?;
+        engine.shutdown().wait();
+
+        let restored = T::restore(&bytes)?;
+        let input_ref = InputRef::named("restored_engine_resolves_inputs_by_name")
+            .expect("Input was registered above");
+        let listener = restored.listen_with(signal, REPLAY).wait();
+        let emitter = restored.emit::<u64>(input_ref).wait();
+        restored.start().wait()?;
+        assert_eq!(listener.recv()?, 42);
+        emitter.send(43)?;
+        assert_eq!(listener.recv()?, 43);
+    }
+
+    #[case]
+    pub fn restored_graph_is_found_by_name() {
+        let name = "restored_graph_is_found_by_name";
+        let bytes = {
+            let (input_ref, signal) = input_named::<u64>(name)?;
+            let emitter = engine.emit::<u64>(input_ref).wait();
+            let listener = engine.listen(add(signal.clone(), signal)).wait();
+            engine.start().wait()?;
+            emitter.send(21)?;
+            assert_eq!(listener.recv()?, 42);
+            engine.snapshot().wait()?
+        };
+        engine.shutdown().wait();
+
+        // Names stay taken, so the restored graph is looked up rather than declared again.
+        assert!(input_named::<u64>(name).is_err());
+        let restored = T::restore(&bytes)?;
+        let signal = Signal::<u64>::named(name).expect("Input was restored above");
+        let input_ref = InputRef::named(name).expect("Input was restored above");
+        let listener = restored
+            .listen_with(add(signal.clone(), signal), REPLAY)
+            .wait();
+        let emitter = restored.emit::<u64>(input_ref).wait();
+        restored.start().wait()?;
+        assert_eq!(listener.recv()?, 42);
+        emitter.send(1)?;
+        assert_eq!(listener.recv()?, 2);
+    }
+}
@@ -0,0 +1,108 @@
+use rig_macros::test_suite;
+
+#[test_suite]
+pub mod observed {
+
+    use std::{
+        sync::Arc,
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use engine_base::{
+        observer::ArithmeticOverflow,
+        observer::Metrics,
+        operators::{add, checked_add, input},
+        waiting::Waiting,
+        Emit, Engine,
+    };
+
+    #[setup]
+    fn setup<T: Engine>(e: (T, Arc<Metrics>)) {
+        let (engine, metrics) = e;
+        let (input_ref, signal) = input::<u64>();
+    }
+
+    #[case]
+    pub fn metrics_count_updates() {
+        let listener = engine.listen(add(signal.clone(), signal)).wait();
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        engine.start().wait()?;
+        emitter.send(20)?;
+        emitter.send(21)?;
+        assert_eq!(listener.recv()?, 40);
+        assert_eq!(listener.recv()?, 42);
+        engine.describe().wait();
+        let report = metrics.report();
+        assert_eq!(report.updates, 2);
+        assert!(report.latency_p50.is_some());
+        assert_eq!(metrics.latency().count(), 2);
+        assert!(report.commands >= 4);
+    }
+
+    #[case]
+    pub fn metrics_count_arithmetic_overflows() {
+        let listener = engine.listen(checked_add(signal.clone(), signal)).wait();
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        engine.start().wait()?;
+        emitter.send(u64::MAX)?;
+        emitter.send(1)?;
+        let overflow = ArithmeticOverflow {
+            left: u64::MAX,
+            right: u64::MAX,
+        };
+        assert_eq!(listener.recv()?, Err(overflow));
+        assert_eq!(listener.recv()?, Ok(2));
+        engine.describe().wait();
+        assert_eq!(metrics.report().arithmetic_overflows, 1);
+    }
+
+    #[case]
+    pub fn metrics_track_live_listeners() {
+        let dropped = engine.listen(signal.clone()).wait();
+        let probe = engine.listen(signal).wait();
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        engine.start().wait()?;
+        assert_eq!(metrics.report().live_listeners, 2);
+        drop(dropped);
+        emitter.send(42)?;
+        assert_eq!(probe.recv()?, 42);
+        engine.describe().wait();
+        let report = metrics.report();
+        assert_eq!(report.live_listeners, 1);
+        assert_eq!(report.listeners_removed, 1);
+    }
+
+    #[case]
+    pub fn metrics_track_prestart_queue_depth() {
+        let listener = engine.listen(signal).wait();
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        for value in 0..3 {
+            emitter.send(value)?;
+        }
+        while metrics.report().queue_depth < 3 {
+            thread::yield_now();
+        }
+        engine.start().wait()?;
+        for value in 0..3 {
+            assert_eq!(listener.recv()?, value);
+        }
+        let report = metrics.report();
+        assert_eq!(report.queue_depth, 0);
+        assert_eq!(report.max_queue_depth, 3);
+    }
+
+    #[case]
+    pub fn metrics_count_disconnected_emitters() {
+        let _listener = engine.listen(signal).wait();
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        engine.start().wait()?;
+        drop(emitter);
+        // Disconnects are noticed by the engine thread, so wait for it a bounded while.
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while metrics.report().emitters_disconnected < 1 && Instant::now() < deadline {
+            thread::yield_now();
+        }
+        assert_eq!(metrics.report().emitters_disconnected, 1);
+    }
+}
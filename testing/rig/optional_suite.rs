@@ -0,0 +1,76 @@
+use rig_macros::test_suite;
+
+#[test_suite]
+pub mod optional {
+
+    use engine_base::{
+        operators::{input, input_named, optional, unwrap_or},
+        waiting::Waiting,
+        Emit, Engine, ListenOptions,
+    };
+
+    const REPLAY: ListenOptions = ListenOptions {
+        replay_current: true,
+    };
+
+    #[setup]
+    fn setup<T: Engine>(e: T) {
+        let engine = e;
+        let (input_ref, signal) = input::<Option<u64>>();
+    }
+
+    #[case]
+    pub fn optional_inputs_start_missing() {
+        let listener = engine.listen_with(signal, REPLAY).wait();
+        let emitter = engine.emit::<Option<u64>>(input_ref).wait();
+        engine.start().wait()?;
+        emitter.send(Some(3))?;
+        emitter.send(None)?;
+        assert_eq!(listener.recv()?, None);
+        assert_eq!(listener.recv()?, Some(3));
+        assert_eq!(listener.recv()?, None);
+    }
+
+    #[case]
+    pub fn unwrap_or_fills_in_missing_values() {
+        let listener = engine.listen_with(unwrap_or(signal, 7), REPLAY).wait();
+        let emitter = engine.emit::<Option<u64>>(input_ref).wait();
+        engine.start().wait()?;
+        emitter.send(Some(2))?;
+        emitter.send(None)?;
+        assert_eq!(listener.recv()?, 7);
+        assert_eq!(listener.recv()?, 2);
+        assert_eq!(listener.recv()?, 7);
+    }
+
+    #[case]
+    pub fn optional_wraps_present_values() {
+        let (_, plain) = input::<u64>();
+        let listener = engine.listen_with(optional(plain), REPLAY).wait();
+        let probe = engine.listen(unwrap_or(signal, 0)).wait();
+        let emitter = engine.emit::<Option<u64>>(input_ref).wait();
+        engine.start().wait()?;
+        emitter.send(Some(1))?;
+        assert_eq!(probe.recv()?, 1);
+        assert_eq!(listener.recv()?, Some(0));
+    }
+
+    #[case]
+    pub fn missing_values_survive_snapshots() {
+        let (input_ref, signal) = input_named::<Option<u64>>("missing_values_survive_snapshots")?;
+        let probe = engine.listen(signal.clone()).wait();
+        let emitter = engine.emit::<Option<u64>>(input_ref).wait();
+        engine.start().wait()?;
+        emitter.send(Some(4))?;
+        emitter.send(None)?;
+        probe.recv()?;
+        probe.recv()?;
+        let bytes = engine.snapshot().wait()?;
+        engine.shutdown().wait();
+
+        let restored = T::restore(&bytes)?;
+        restored.start().wait()?;
+        let listener = restored.listen_with(unwrap_or(signal, 9), REPLAY).wait();
+        assert_eq!(listener.recv()?, 9);
+    }
+}
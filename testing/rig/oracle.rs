@@ -0,0 +1,52 @@
+use crate::{
+    differential::Observed,
+    script::{Script, SignalSpec, Step},
+};
+
+// Evaluates a signal straight from its spec, with unset inputs at zero. Additions wrap around,
+// like `add`.
+pub fn evaluate(signal: &SignalSpec, values: &[u64]) -> u64 {
+    match signal {
+        SignalSpec::Input(input) => values[*input],
+        SignalSpec::Add(left, right) => {
+            evaluate(left, values).wrapping_add(evaluate(right, values))
+        }
+    }
+}
+
+// Whether a signal is built on the given input.
+fn reads(signal: &SignalSpec, input: usize) -> bool {
+    match signal {
+        SignalSpec::Input(read) => *read == input,
+        SignalSpec::Add(left, right) => reads(left, input) || reads(right, input),
+    }
+}
+
+// What every listener of `script` should observe: one value per update of an input it depends
+// on, plus the current value when listening with replay.
+pub fn expected(script: &Script) -> Observed {
+    let mut values = vec![0; script.inputs];
+    let mut listeners = Vec::new();
+    let mut observed = Observed::new();
+    for step in &script.steps {
+        match step {
+            Step::Listen { signal, replay } => {
+                let mut received = Vec::new();
+                if *replay {
+                    received.push(evaluate(signal, &values));
+                }
+                listeners.push(signal);
+                observed.push(received);
+            }
+            Step::Emit { input, value } => {
+                values[*input] = *value;
+                for (signal, received) in listeners.iter().zip(&mut observed) {
+                    if reads(signal, *input) {
+                        received.push(evaluate(signal, &values));
+                    }
+                }
+            }
+        }
+    }
+    observed
+}
@@ -0,0 +1,100 @@
+use rig_macros::test_suite;
+
+#[test_suite]
+pub mod overflow {
+
+    use engine_base::{
+        observer::ArithmeticOverflow,
+        operators::{add, checked_add, errors, input, recover, saturating_add},
+        waiting::Waiting,
+        Emit, Engine,
+    };
+
+    #[setup]
+    fn setup<T: Engine>(e: T) {
+        let engine = e;
+        let (input_ref, signal) = input::<u64>();
+        let (max_ref, max) = input::<u64>();
+    }
+
+    #[case]
+    pub fn add_wraps_around() {
+        let listener = engine.listen(add(signal.clone(), max.clone())).wait();
+        let probe = engine.listen(max).wait();
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        let max_emitter = engine.emit::<u64>(max_ref).wait();
+        engine.start().wait()?;
+        max_emitter.send(u64::MAX)?;
+        probe.recv()?;
+        emitter.send(2)?;
+        assert_eq!(listener.recv()?, u64::MAX);
+        assert_eq!(listener.recv()?, 1);
+    }
+
+    #[case]
+    pub fn saturating_add_stops_at_the_maximum() {
+        let listener = engine
+            .listen(saturating_add(signal.clone(), max.clone()))
+            .wait();
+        let probe = engine.listen(max).wait();
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        let max_emitter = engine.emit::<u64>(max_ref).wait();
+        engine.start().wait()?;
+        max_emitter.send(u64::MAX - 1)?;
+        probe.recv()?;
+        emitter.send(2)?;
+        assert_eq!(listener.recv()?, u64::MAX - 1);
+        assert_eq!(listener.recv()?, u64::MAX);
+    }
+
+    #[case]
+    pub fn checked_add_fails_on_overflow() {
+        let sum = checked_add(signal.clone(), max.clone());
+        let listener = engine.listen(sum.clone()).wait();
+        let fallback = engine
+            .listen(recover(sum, |_: ArithmeticOverflow| 0))
+            .wait();
+        let probe = engine.listen(max).wait();
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        let max_emitter = engine.emit::<u64>(max_ref).wait();
+        engine.start().wait()?;
+        max_emitter.send(u64::MAX)?;
+        probe.recv()?;
+        emitter.send(1)?;
+        emitter.send(0)?;
+        let overflow = ArithmeticOverflow {
+            left: 1,
+            right: u64::MAX,
+        };
+        assert_eq!(listener.recv()?, Ok(u64::MAX));
+        assert_eq!(listener.recv()?, Err(overflow));
+        assert_eq!(listener.recv()?, Ok(u64::MAX));
+        assert_eq!(fallback.recv()?, u64::MAX);
+        assert_eq!(fallback.recv()?, 0);
+        assert_eq!(fallback.recv()?, u64::MAX);
+    }
+
+    #[case]
+    pub fn checked_overflows_reach_error_streams() {
+        let listener = engine
+            .listen(errors(checked_add(signal, max.clone())))
+            .wait();
+        let probe = engine.listen(max).wait();
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        let max_emitter = engine.emit::<u64>(max_ref).wait();
+        engine.start().wait()?;
+        max_emitter.send(u64::MAX)?;
+        probe.recv()?;
+        emitter.send(1)?;
+        emitter.send(0)?;
+        emitter.send(2)?;
+        for left in [1, 2] {
+            let overflow = ArithmeticOverflow {
+                left,
+                right: u64::MAX,
+            };
+            assert_eq!(listener.recv()?, overflow);
+        }
+        assert!(listener.try_recv().is_err());
+    }
+}
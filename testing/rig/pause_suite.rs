@@ -0,0 +1,97 @@
+use rig_macros::test_suite;
+
+#[test_suite]
+pub mod pause {
+
+    use engine_base::{
+        lifecycle::{Lifecycle, LifecycleError},
+        operators::input,
+        waiting::{MaybeWaiting, Waiting},
+        Emit, Engine,
+    };
+
+    #[setup]
+    fn setup<T: Engine>(e: T) {
+        let engine = e;
+        let (input_ref, signal) = input::<u64>();
+    }
+
+    #[case]
+    pub fn paused_engine_delivers_updates_on_resume() {
+        let listener = engine.listen(signal).wait();
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        engine.start().wait()?;
+        engine.pause().wait()?;
+        assert_eq!(engine.lifecycle(), Lifecycle::Paused);
+        for value in 1..=3 {
+            emitter.send(value)?;
+        }
+        engine.describe().wait();
+        assert!(listener.try_recv().is_err());
+        engine.resume().wait()?;
+        for value in 1..=3 {
+            assert_eq!(listener.recv()?, value);
+        }
+        emitter.send(4)?;
+        assert_eq!(listener.recv()?, 4);
+    }
+
+    #[case]
+    pub fn engine_can_be_paused_repeatedly() {
+        let listener = engine.listen(signal).wait();
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        engine.start().immediate()?;
+        for value in 1..=3 {
+            engine.pause().immediate()?;
+            emitter.send(value)?;
+            engine.resume().immediate()?;
+        }
+        for value in 1..=3 {
+            assert_eq!(listener.recv()?, value);
+        }
+    }
+
+    #[case]
+    pub fn pause_requires_running_engine() {
+        assert_eq!(
+            engine.pause().wait(),
+            Err(LifecycleError {
+                from: Lifecycle::Created,
+                to: Lifecycle::Paused,
+            })
+        );
+        let listener = engine.listen(signal).wait();
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        engine.start().wait()?;
+        emitter.send(42)?;
+        assert_eq!(listener.recv()?, 42);
+    }
+
+    #[case]
+    pub fn resume_requires_paused_engine() {
+        engine.start().wait()?;
+        assert_eq!(
+            engine.resume().wait(),
+            Err(LifecycleError {
+                from: Lifecycle::Running,
+                to: Lifecycle::Running,
+            })
+        );
+        let listener = engine.listen(signal).wait();
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        emitter.send(42)?;
+        assert_eq!(listener.recv()?, 42);
+    }
+
+    #[case]
+    pub fn paused_engine_cannot_be_started() {
+        engine.start().wait()?;
+        engine.pause().wait()?;
+        assert!(engine.start().wait().is_err());
+        let listener = engine.listen(signal).wait();
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        emitter.send(42)?;
+        engine.resume().wait()?;
+        assert_eq!(listener.recv()?, 42);
+    }
+}
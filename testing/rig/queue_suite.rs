@@ -0,0 +1,167 @@
+use rig_macros::test_suite;
+
+// Expects the engine to be configured with prestart and pause queues holding at most two updates,
+// coalesced to the latest value per input. A full prestart queue rejects new updates, while a full
+// pause queue drops the oldest one.
+#[test_suite]
+pub mod queues {
+
+    use std::{
+        sync::Arc,
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use engine_base::{
+        observer::Metrics,
+        operators::{
+            collection::{Change, Map},
+            input,
+        },
+        waiting::Waiting,
+        Emit, Engine,
+    };
+
+    #[setup]
+    fn setup<T: Engine>(e: (T, Arc<Metrics>)) {
+        let (engine, metrics) = e;
+        let (first_ref, first) = input::<u64>();
+        let (second_ref, second) = input::<u64>();
+        let (third_ref, third) = input::<u64>();
+    }
+
+    // Updates reach the queues on the engine thread, so wait for them a bounded while.
+    fn wait_for_queued(metrics: &Metrics, count: u64) -> anyhow::Result<()> {
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while metrics.report().updates_queued < count {
+            anyhow::ensure!(Instant::now() < deadline, "fewer than {count} updates were queued");
+            thread::yield_now();
+        }
+        Ok(())
+    }
+
+    #[case]
+    pub fn pause_queue_coalesces_per_input() {
+        let first_listener = engine.listen(first).wait();
+        let second_listener = engine.listen(second).wait();
+        let first_emitter = engine.emit::<u64>(first_ref).wait();
+        let second_emitter = engine.emit::<u64>(second_ref).wait();
+        engine.start().wait()?;
+        engine.pause().wait()?;
+        first_emitter.send(1)?;
+        first_emitter.send(2)?;
+        second_emitter.send(10)?;
+        first_emitter.send(3)?;
+        wait_for_queued(&metrics, 4)?;
+        assert!(metrics.report().max_queue_depth <= 2);
+        engine.resume().wait()?;
+        assert_eq!(first_listener.recv()?, 3);
+        assert_eq!(second_listener.recv()?, 10);
+        engine.describe().wait();
+        assert!(first_listener.try_recv().is_err());
+        assert_eq!(metrics.report().updates_dropped, 0);
+    }
+
+    #[case]
+    pub fn pause_queue_drops_oldest_when_full() {
+        let first_listener = engine.listen(first).wait();
+        let second_listener = engine.listen(second).wait();
+        let third_listener = engine.listen(third).wait();
+        let first_emitter = engine.emit::<u64>(first_ref).wait();
+        let second_emitter = engine.emit::<u64>(second_ref).wait();
+        let third_emitter = engine.emit::<u64>(third_ref).wait();
+        engine.start().wait()?;
+        engine.pause().wait()?;
+        first_emitter.send(1)?;
+        wait_for_queued(&metrics, 1)?;
+        second_emitter.send(2)?;
+        wait_for_queued(&metrics, 2)?;
+        third_emitter.send(3)?;
+        wait_for_queued(&metrics, 3)?;
+        engine.resume().wait()?;
+        assert_eq!(second_listener.recv()?, 2);
+        assert_eq!(third_listener.recv()?, 3);
+        engine.describe().wait();
+        assert!(first_listener.try_recv().is_err());
+        assert_eq!(metrics.report().updates_dropped, 1);
+    }
+
+    #[case]
+    pub fn prestart_queue_coalesces_per_input() {
+        let first_listener = engine.listen(first).wait();
+        let second_listener = engine.listen(second).wait();
+        let first_emitter = engine.emit::<u64>(first_ref).wait();
+        let second_emitter = engine.emit::<u64>(second_ref).wait();
+        for value in 1..=5 {
+            first_emitter.send(value)?;
+        }
+        second_emitter.send(10)?;
+        wait_for_queued(&metrics, 6)?;
+        engine.start().wait()?;
+        assert_eq!(first_listener.recv()?, 5);
+        assert_eq!(second_listener.recv()?, 10);
+        engine.describe().wait();
+        assert!(first_listener.try_recv().is_err());
+        let report = metrics.report();
+        assert_eq!(report.max_queue_depth, 2);
+        assert_eq!(report.queue_overflows, 0);
+    }
+
+    #[case]
+    pub fn coalesced_maps_keep_every_change() {
+        let (entries_ref, entries) = input::<Map<u64, u64>>();
+        let first_listener = engine.listen(first).wait();
+        let entries_listener = engine.listen(entries).wait();
+        let first_emitter = engine.emit::<u64>(first_ref).wait();
+        let entries_emitter = engine.emit::<Map<u64, u64>>(entries_ref).wait();
+        first_emitter.send(1)?;
+        entries_emitter.send(Map::new().insert(1, 10))?;
+        entries_emitter.send(Map::new().insert(2, 20))?;
+        entries_emitter.send(Map::new().remove(1))?;
+        first_emitter.send(2)?;
+        wait_for_queued(&metrics, 5)?;
+        engine.start().wait()?;
+        assert_eq!(first_listener.recv()?, 2);
+        assert_eq!(
+            entries_listener.recv()?.into_changes(),
+            vec![
+                Change::Insert(1, 10),
+                Change::Insert(2, 20),
+                Change::Remove(1)
+            ]
+        );
+        engine.describe().wait();
+        assert!(entries_listener.try_recv().is_err());
+    }
+
+    #[case]
+    pub fn prestart_queue_rejects_when_full() {
+        let first_emitter = engine.emit::<u64>(first_ref).wait();
+        let second_emitter = engine.emit::<u64>(second_ref).wait();
+        let third_emitter = engine.emit::<u64>(third_ref).wait();
+        first_emitter.send(1)?;
+        second_emitter.send(2)?;
+        assert!(third_emitter.send(3).is_err());
+        let report = metrics.report();
+        assert_eq!(report.queue_overflows, 1);
+        assert_eq!(report.updates_dropped, 1);
+    }
+
+    #[case]
+    pub fn prestart_sends_fail_when_full() {
+        let first_listener = engine.listen(first).wait();
+        let second_listener = engine.listen(second).wait();
+        let third_listener = engine.listen(third).wait();
+        let first_emitter = engine.emit::<u64>(first_ref).wait();
+        let second_emitter = engine.emit::<u64>(second_ref).wait();
+        let third_emitter = engine.emit::<u64>(third_ref).wait();
+        first_emitter.send(1)?;
+        second_emitter.send(2)?;
+        assert_eq!(third_emitter.send(3).map_err(|error| error.0), Err(3));
+        engine.start().wait()?;
+        assert_eq!(first_listener.recv()?, 1);
+        assert_eq!(second_listener.recv()?, 2);
+        third_emitter.send(4)?;
+        assert_eq!(third_listener.recv()?, 4);
+    }
+}
@@ -0,0 +1,97 @@
+use rig_macros::test_suite;
+
+#[test_suite]
+pub mod replay {
+
+    use engine_base::{
+        operators::input,
+        waiting::{MaybeWaiting, Waiting},
+        Emit, Engine, ListenOptions,
+    };
+
+    const REPLAY: ListenOptions = ListenOptions {
+        replay_current: true,
+    };
+
+    #[setup]
+    fn setup<T: Engine>(e: T) {
+        let engine = e;
+        let (input_ref, signal) = input::<u64>();
+    }
+
+    #[case]
+    pub fn replay_sends_current_value__already_running__register_on_running() {
+        engine.start().wait()?;
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        let probe = engine.listen(signal.clone()).wait();
+        emitter.send(42)?;
+        assert_eq!(probe.recv()?, 42);
+        let listener = engine.listen_with(signal, REPLAY).wait();
+        assert_eq!(listener.recv()?, 42);
+        emitter.send(43)?;
+        assert_eq!(listener.recv()?, 43);
+    }
+
+    #[case]
+    pub fn replay_sends_current_value__register_before_start() {
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        let listener = engine.listen_with(signal, REPLAY).wait();
+        emitter.send(42)?;
+        engine.start().immediate()?;
+        assert_eq!(listener.recv()?, 0);
+        assert_eq!(listener.recv()?, 42);
+    }
+
+    #[case]
+    pub fn replay_sends_current_value__start_after_emitter_register() {
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        engine.start().wait()?;
+        let probe = engine.listen(signal.clone()).wait();
+        emitter.send(42)?;
+        assert_eq!(probe.recv()?, 42);
+        let listener = engine.listen_with(signal, REPLAY).wait();
+        assert_eq!(listener.recv()?, 42);
+    }
+
+    #[case]
+    pub fn replay_sends_current_value__reversed__already_running() {
+        engine.start().wait()?;
+        let listener = engine.listen_with(signal, REPLAY).wait();
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        emitter.send(42)?;
+        assert_eq!(listener.recv()?, 0);
+        assert_eq!(listener.recv()?, 42);
+    }
+
+    #[case]
+    pub fn replay_sends_current_value__reversed__register_before_start() {
+        let listener = engine.listen_with(signal, REPLAY).wait();
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        emitter.send(42)?;
+        engine.start().immediate()?;
+        assert_eq!(listener.recv()?, 0);
+        assert_eq!(listener.recv()?, 42);
+    }
+
+    #[case]
+    pub fn replay_sends_current_value__reversed__start_after_emitter_register() {
+        let listener = engine.listen_with(signal, REPLAY).wait();
+        engine.start().wait()?;
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        emitter.send(42)?;
+        assert_eq!(listener.recv()?, 0);
+        assert_eq!(listener.recv()?, 42);
+    }
+
+    #[case]
+    pub fn listener_without_replay_skips_current_value() {
+        engine.start().wait()?;
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        let probe = engine.listen(signal.clone()).wait();
+        emitter.send(42)?;
+        assert_eq!(probe.recv()?, 42);
+        let listener = engine.listen(signal).wait();
+        emitter.send(43)?;
+        assert_eq!(listener.recv()?, 43);
+    }
+}
@@ -0,0 +1,101 @@
+use rig_macros::test_suite;
+
+#[test_suite]
+pub mod result {
+
+    use std::sync::Arc;
+
+    use engine_base::{
+        operators::{errors, input, input_named, map_err, recover},
+        waiting::Waiting,
+        Emit, Engine, ListenOptions,
+    };
+
+    const REPLAY: ListenOptions = ListenOptions {
+        replay_current: true,
+    };
+
+    #[setup]
+    fn setup<T: Engine>(e: T) {
+        let engine = e;
+        let (input_ref, signal) = input::<Result<u64, Arc<str>>>();
+        let boom: Arc<str> = Arc::from("boom");
+    }
+
+    #[case]
+    pub fn errors_flow_like_values() {
+        let listener = engine.listen_with(signal, REPLAY).wait();
+        let emitter = engine.emit::<Result<u64, Arc<str>>>(input_ref).wait();
+        engine.start().wait()?;
+        emitter.send(Err(Arc::clone(&boom)))?;
+        emitter.send(Ok(3))?;
+        assert_eq!(listener.recv()?, Ok(0));
+        assert_eq!(listener.recv()?, Err(boom));
+        assert_eq!(listener.recv()?, Ok(3));
+    }
+
+    #[case]
+    pub fn map_err_transforms_errors_only() {
+        let mapped = map_err(signal, |error: Arc<str>| error.len() as u64);
+        let listener = engine.listen(mapped).wait();
+        let emitter = engine.emit::<Result<u64, Arc<str>>>(input_ref).wait();
+        engine.start().wait()?;
+        emitter.send(Ok(7))?;
+        emitter.send(Err(boom))?;
+        assert_eq!(listener.recv()?, Ok(7));
+        assert_eq!(listener.recv()?, Err(4));
+    }
+
+    #[case]
+    pub fn recover_replaces_errors() {
+        let listener = engine
+            .listen(recover(signal, |error: Arc<str>| error.len() as u64 * 10))
+            .wait();
+        let emitter = engine.emit::<Result<u64, Arc<str>>>(input_ref).wait();
+        engine.start().wait()?;
+        emitter.send(Err(boom))?;
+        emitter.send(Ok(1))?;
+        assert_eq!(listener.recv()?, 40);
+        assert_eq!(listener.recv()?, 1);
+    }
+
+    #[case]
+    pub fn errors_only_reports_failures() {
+        let listener = engine.listen_with(errors(signal.clone()), REPLAY).wait();
+        let probe = engine.listen(signal).wait();
+        let emitter = engine.emit::<Result<u64, Arc<str>>>(input_ref).wait();
+        engine.start().wait()?;
+        emitter.send(Ok(1))?;
+        emitter.send(Err(Arc::clone(&boom)))?;
+        emitter.send(Ok(2))?;
+        emitter.send(Err(Arc::from("again")))?;
+        assert_eq!(probe.recv()?, Ok(1));
+        assert!(probe.recv()?.is_err());
+        assert_eq!(probe.recv()?, Ok(2));
+        assert!(probe.recv()?.is_err());
+        assert_eq!(listener.recv()?, boom);
+        assert_eq!(&*listener.recv()?, "again");
+        assert!(listener.try_recv().is_err());
+    }
+
+    #[case]
+    pub fn errors_survive_snapshots() {
+        let (input_ref, signal) = input_named::<Result<u64, Arc<str>>>("errors_survive_snapshots")?;
+        let mapped = map_err(signal.clone(), |error: Arc<str>| error.len() as u64);
+        let probe = engine.listen(mapped.clone()).wait();
+        let emitter = engine.emit::<Result<u64, Arc<str>>>(input_ref).wait();
+        engine.start().wait()?;
+        emitter.send(Err(boom))?;
+        assert_eq!(probe.recv()?, Err(4));
+        let bytes = engine.snapshot().wait()?;
+        engine.shutdown().wait();
+
+        let restored = T::restore(&bytes)?;
+        restored.start().wait()?;
+        let listener = restored.listen_with(signal, REPLAY).wait();
+        assert_eq!(listener.recv()?, Err(Arc::from("boom")));
+        // Closures are not persisted, so the node is rebuilt from the restored input.
+        let listener = restored.listen_with(mapped, REPLAY).wait();
+        assert_eq!(listener.recv()?, Err(4));
+    }
+}
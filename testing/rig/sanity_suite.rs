@@ -2,24 +2,45 @@ use rig_macros::test_suite;
 
 #[test_suite]
 pub mod sanity {
+    use std::time::Duration;
+
     use engine_base::{
+        operators::{add, input},
         waiting::{MaybeWaiting, Waiting},
-        Engine,
+        Emit, Engine,
     };
 
+    const TIMEOUT: Duration = Duration::from_secs(1);
+
     #[setup]
     fn setup<T: Engine>(e: T) {
         let engine = e;
     }
     #[case]
     fn engine_can_be_started_and_stopped() {
-        engine.start().wait();
+        engine.start().wait()?;
         engine.shutdown().wait();
     }
 
     #[case]
     fn engine_can_be_started_and_stopped_without_waiting() {
-        engine.start().immediate();
+        engine.start().immediate()?;
+        engine.shutdown().wait();
+    }
+
+    // Add nodes used to keep the value computed when they were registered and never saw updates.
+    #[case]
+    fn engine_propagates_input_updates_through_add() {
+        let (left_ref, left) = input::<u64>();
+        let (right_ref, right) = input::<u64>();
+        let listener = engine.listen(add(left, right)).wait();
+        let left_emitter = engine.emit::<u64>(left_ref).wait();
+        let right_emitter = engine.emit::<u64>(right_ref).wait();
+        engine.start().wait()?;
+        left_emitter.send(40)?;
+        assert_eq!(listener.recv_timeout(TIMEOUT)?, 40);
+        right_emitter.send(2)?;
+        assert_eq!(listener.recv_timeout(TIMEOUT)?, 42);
         engine.shutdown().wait();
     }
 }
@@ -0,0 +1,136 @@
+use std::fmt::{self, Display, Formatter};
+
+use engine_base::operators::{add, input, InputRef, Signal};
+
+// xorshift64*, so scripts can be regenerated from their seed alone.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    pub fn below(&mut self, bound: usize) -> usize {
+        usize::try_from(self.next_u64() % bound as u64).expect("Value is below a usize bound")
+    }
+
+    pub fn chance(&mut self, percent: usize) -> bool {
+        self.below(100) < percent
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SignalSpec {
+    Input(usize),
+    Add(Box<SignalSpec>, Box<SignalSpec>),
+}
+
+impl SignalSpec {
+    pub fn random(rng: &mut Rng, inputs: usize, depth: usize) -> Self {
+        if depth == 0 || rng.chance(40) {
+            SignalSpec::Input(rng.below(inputs))
+        } else {
+            SignalSpec::Add(
+                Box::new(Self::random(rng, inputs, depth - 1)),
+                Box::new(Self::random(rng, inputs, depth - 1)),
+            )
+        }
+    }
+
+    pub fn build(&self, inputs: &[Signal<u64>]) -> Signal<u64> {
+        match self {
+            SignalSpec::Input(input) => inputs[*input].clone(),
+            SignalSpec::Add(left, right) => add(left.build(inputs), right.build(inputs)),
+        }
+    }
+}
+
+impl Display for SignalSpec {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SignalSpec::Input(input) => write!(f, "s{input}"),
+            SignalSpec::Add(left, right) => write!(f, "add({left}, {right})"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Step {
+    Listen { signal: SignalSpec, replay: bool },
+    Emit { input: usize, value: u64 },
+}
+
+impl Display for Step {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Step::Listen {
+                signal,
+                replay: false,
+            } => write!(f, "listen({signal})"),
+            Step::Listen {
+                signal,
+                replay: true,
+            } => write!(f, "listen_with({signal}, REPLAY)"),
+            Step::Emit { input, value } => write!(f, "emit(s{input}, {value})"),
+        }
+    }
+}
+
+// A sequence of listens and emits against a started engine. Values stay small enough that sums
+// never overflow.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Script {
+    pub inputs: usize,
+    pub steps: Vec<Step>,
+}
+
+impl Script {
+    pub fn random(rng: &mut Rng, inputs: usize, steps: usize) -> Self {
+        let steps = (0..steps)
+            .map(|_| {
+                if rng.chance(30) {
+                    Step::Listen {
+                        signal: SignalSpec::random(rng, inputs, 3),
+                        replay: rng.chance(50),
+                    }
+                } else {
+                    Step::Emit {
+                        input: rng.below(inputs),
+                        value: rng.below(1000) as u64,
+                    }
+                }
+            })
+            .collect();
+        Self { inputs, steps }
+    }
+
+    pub fn inputs(&self) -> (Vec<InputRef>, Vec<Signal<u64>>) {
+        (0..self.inputs).map(|_| input::<u64>()).unzip()
+    }
+
+    pub fn without(&self, start: usize, len: usize) -> Self {
+        let mut steps = self.steps.clone();
+        steps.drain(start..(start + len).min(steps.len()));
+        Self {
+            inputs: self.inputs,
+            steps,
+        }
+    }
+}
+
+impl Display for Script {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "inputs: {}", self.inputs)?;
+        for (id, step) in self.steps.iter().enumerate() {
+            writeln!(f, "{id:>4}: {step}")?;
+        }
+        Ok(())
+    }
+}
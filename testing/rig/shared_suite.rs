@@ -0,0 +1,63 @@
+use rig_macros::test_suite;
+
+#[test_suite]
+pub mod shared {
+
+    use std::sync::Arc;
+
+    use engine_base::{
+        operators::{input, input_named},
+        waiting::Waiting,
+        Emit, Engine, ListenOptions,
+    };
+
+    const REPLAY: ListenOptions = ListenOptions {
+        replay_current: true,
+    };
+
+    #[setup]
+    fn setup<T: Engine>(e: T) {
+        let engine = e;
+        let (input_ref, signal) = input::<Arc<str>>();
+        let value: Arc<str> = Arc::from("a value that is never copied");
+    }
+
+    #[case]
+    pub fn listeners_share_the_emitted_value() {
+        let first = engine.listen(signal.clone()).wait();
+        let second = engine.listen(signal).wait();
+        let emitter = engine.emit::<Arc<str>>(input_ref).wait();
+        engine.start().wait()?;
+        emitter.send(Arc::clone(&value))?;
+        assert!(Arc::ptr_eq(&first.recv()?, &value));
+        assert!(Arc::ptr_eq(&second.recv()?, &value));
+    }
+
+    #[case]
+    pub fn replay_shares_the_current_value() {
+        let probe = engine.listen(signal.clone()).wait();
+        let emitter = engine.emit::<Arc<str>>(input_ref).wait();
+        engine.start().wait()?;
+        emitter.send(Arc::clone(&value))?;
+        probe.recv()?;
+        let listener = engine.listen_with(signal, REPLAY).wait();
+        assert!(Arc::ptr_eq(&listener.recv()?, &value));
+    }
+
+    #[case]
+    pub fn shared_values_survive_snapshots() {
+        let (input_ref, signal) = input_named::<Arc<str>>("shared_values_survive_snapshots")?;
+        let probe = engine.listen(signal.clone()).wait();
+        let emitter = engine.emit::<Arc<str>>(input_ref).wait();
+        engine.start().wait()?;
+        emitter.send(Arc::clone(&value))?;
+        probe.recv()?;
+        let bytes = engine.snapshot().wait()?;
+        engine.shutdown().wait();
+
+        let restored = T::restore(&bytes)?;
+        restored.start().wait()?;
+        let listener = restored.listen_with(signal, REPLAY).wait();
+        assert_eq!(listener.recv()?, value);
+    }
+}
@@ -0,0 +1,79 @@
+use rig_macros::test_suite;
+
+#[test_suite]
+pub mod shutdown {
+
+    use std::thread;
+
+    use engine_base::{
+        operators::{add, input},
+        waiting::Waiting,
+        Emit, Engine,
+    };
+
+    #[setup]
+    fn setup<T: Engine>(e: T) {
+        let engine = e;
+        let (input_ref, signal) = input::<u64>();
+    }
+
+    #[case]
+    pub fn graceful_shutdown_drains_pending_updates() {
+        let listener = engine.listen(signal).wait();
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        for value in 1..=100 {
+            emitter.send(value)?;
+        }
+        assert_eq!(engine.shutdown_graceful().wait(), 100);
+        assert_eq!(
+            listener.iter().collect::<Vec<_>>(),
+            (1..=100).collect::<Vec<_>>()
+        );
+    }
+
+    #[case]
+    pub fn graceful_shutdown_drains_paused_engine() {
+        let listener = engine.listen(add(signal.clone(), signal)).wait();
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        engine.start().wait()?;
+        engine.pause().wait()?;
+        for value in 1..=3 {
+            emitter.send(value)?;
+        }
+        assert_eq!(engine.shutdown_graceful().wait(), 3);
+        assert_eq!(listener.iter().collect::<Vec<_>>(), vec![2, 4, 6]);
+    }
+
+    #[case]
+    pub fn graceful_shutdown_disconnects_listeners() {
+        let listener = engine.listen(signal).wait();
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        engine.start().wait()?;
+        emitter.send(1)?;
+        assert_eq!(listener.recv()?, 1);
+        drop(emitter);
+        // The count covers every update the engine processed, not only the drained ones.
+        assert_eq!(engine.shutdown_graceful().wait(), 1);
+        assert!(listener.recv().is_err());
+    }
+
+    #[case]
+    pub fn graceful_shutdown_refuses_later_sends() {
+        let listener = engine.listen(signal).wait();
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        engine.start().wait()?;
+        let (sent, processed) = thread::scope(|scope| {
+            let sender = scope.spawn(move || {
+                let mut sent = 0;
+                while emitter.send(sent).is_ok() {
+                    sent += 1;
+                }
+                sent
+            });
+            assert_eq!(listener.recv(), Ok(0));
+            let processed = engine.shutdown_graceful().wait();
+            (sender.join().expect("Sender panicked"), processed)
+        });
+        assert_eq!(u64::try_from(processed)?, sent);
+    }
+}
@@ -0,0 +1,235 @@
+use rig_macros::test_suite;
+
+#[test_suite]
+pub mod snapshot {
+
+    use std::time::Duration;
+
+    use engine_base::{
+        observer::ArithmeticOverflow,
+        operators::{
+            add, checked_add, collection::Change, input, input_named, last_n, ok_or, optional,
+            recover, switch, window_sum, Signal,
+        },
+        snapshot::SnapshotError,
+        waiting::{MaybeWaiting, Waiting},
+        Emit, Engine, ListenOptions,
+    };
+
+    const REPLAY: ListenOptions = ListenOptions {
+        replay_current: true,
+    };
+
+    #[setup]
+    fn setup<T: Engine>(e: T) {
+        let engine = e;
+        let (input_ref, signal) = input::<u64>();
+    }
+
+    #[case]
+    pub fn restored_engine_keeps_values() {
+        let (input_ref, signal) = input_named::<u64>("restored_engine_keeps_values")?;
+        engine.start().wait()?;
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        let probe = engine.listen(signal.clone()).wait();
+        emitter.send(42)?;
+        assert_eq!(probe.recv()?, 42);
+        let bytes = engine.snapshot().wait()?;
+        engine.shutdown().wait();
+
+        let restored = T::restore(&bytes)?;
+        restored.start().wait()?;
+        let listener = restored.listen_with(signal, REPLAY).wait();
+        assert_eq!(listener.recv()?, 42);
+    }
+
+    #[case]
+    pub fn restored_engine_accepts_new_emitters() {
+        let (input_ref, signal) = input_named::<u64>("restored_engine_accepts_new_emitters")?;
+        let bytes = engine.snapshot().wait()?;
+        engine.shutdown().wait();
+
+        let restored = T::restore(&bytes)?;
+        let listener = restored.listen(signal).wait();
+        let emitter = restored.emit::<u64>(input_ref).wait();
+        restored.start().wait()?;
+        emitter.send(42)?;
+        assert_eq!(listener.recv()?, 42);
+    }
+
+    #[case]
+    pub fn snapshot_is_stable() {
+        let (input_ref, signal) = input_named::<u64>("snapshot_is_stable")?;
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        let listener = engine.listen(signal).wait();
+        engine.start().wait()?;
+        emitter.send(42)?;
+        assert_eq!(listener.recv()?, 42);
+        let bytes = engine.snapshot().wait()?;
+
+        let restored = T::restore(&bytes)?;
+        assert_eq!(restored.snapshot().wait()?, bytes);
+    }
+
+    #[case]
+    pub fn restored_checked_add_keeps_its_overflow() {
+        let (input_ref, signal) = input_named::<u64>("restored_checked_add_keeps_its_overflow")?;
+        let sum = checked_add(signal.clone(), signal);
+        let probe = engine.listen(sum.clone()).wait();
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        engine.start().wait()?;
+        emitter.send(u64::MAX)?;
+        let overflow = ArithmeticOverflow {
+            left: u64::MAX,
+            right: u64::MAX,
+        };
+        assert_eq!(probe.recv()?, Err(overflow));
+        let bytes = engine.snapshot().wait()?;
+        engine.shutdown().wait();
+
+        let restored = T::restore(&bytes)?;
+        restored.start().wait()?;
+        let listener = restored.listen_with(sum, REPLAY).wait();
+        assert_eq!(listener.recv()?, Err(overflow));
+        assert_eq!(restored.snapshot().wait()?, bytes);
+    }
+
+    #[case]
+    pub fn restored_window_sum_keeps_its_history() {
+        let (input_ref, signal) = input_named::<u64>("restored_window_sum_keeps_its_history")?;
+        let summed = window_sum(signal, Duration::from_secs(3600));
+        let probe = engine.listen(summed.clone()).wait();
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        engine.start().wait()?;
+        emitter.send(1)?;
+        emitter.send(2)?;
+        assert_eq!(probe.recv()?, 1);
+        assert_eq!(probe.recv()?, 3);
+        let bytes = engine.snapshot().wait()?;
+        engine.shutdown().wait();
+
+        let restored = T::restore(&bytes)?;
+        let listener = restored.listen_with(summed, REPLAY).wait();
+        let emitter = restored.emit::<u64>(input_ref).wait();
+        restored.start().wait()?;
+        assert_eq!(listener.recv()?, 3);
+        emitter.send(4)?;
+        assert_eq!(listener.recv()?, 7);
+    }
+
+    #[case]
+    pub fn restored_last_n_keeps_its_history() {
+        let (input_ref, signal) = input_named::<u64>("restored_last_n_keeps_its_history")?;
+        let latest = last_n(signal, 2);
+        let probe = engine.listen(latest.clone()).wait();
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        engine.start().wait()?;
+        for _ in 0..3 {
+            emitter.send(10)?;
+            probe.recv()?;
+        }
+        let bytes = engine.snapshot().wait()?;
+        engine.shutdown().wait();
+
+        let restored = T::restore(&bytes)?;
+        let listener = restored.listen(latest).wait();
+        let emitter = restored.emit::<u64>(input_ref).wait();
+        restored.start().wait()?;
+        emitter.send(20)?;
+        assert_eq!(
+            listener.recv()?.into_changes(),
+            vec![Change::Insert(3, 20), Change::Remove(1)]
+        );
+    }
+
+    #[case]
+    pub fn restored_switch_keeps_its_selection() {
+        let (selector_ref, selector) =
+            input_named::<Signal<u64>>("restored_switch_keeps_its_selection/selector")?;
+        let (input_ref, signal) = input_named::<u64>("restored_switch_keeps_its_selection")?;
+        let followed = switch(selector);
+        let probe = engine.listen(followed.clone()).wait();
+        let input_probe = engine.listen(signal.clone()).wait();
+        let selector_emitter = engine.emit::<Signal<u64>>(selector_ref).wait();
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        engine.start().wait()?;
+        emitter.send(2)?;
+        input_probe.recv()?;
+        // The selected node is only registered once it is selected, so it comes after the switch.
+        selector_emitter.send(add(signal.clone(), signal))?;
+        assert_eq!(probe.recv()?, 4);
+        let bytes = engine.snapshot().wait()?;
+        engine.shutdown().wait();
+
+        let restored = T::restore(&bytes)?;
+        let listener = restored.listen_with(followed, REPLAY).wait();
+        let emitter = restored.emit::<u64>(input_ref).wait();
+        restored.start().wait()?;
+        assert_eq!(listener.recv()?, 4);
+        emitter.send(3)?;
+        assert_eq!(listener.recv()?, 6);
+    }
+
+    #[case]
+    pub fn histories_built_on_closures_are_not_persisted() {
+        let (input_ref, signal) =
+            input_named::<u64>("histories_built_on_closures_are_not_persisted")?;
+        let recovered = recover(ok_or(optional(signal), 0_u64), |error: u64| error);
+        let latest = last_n(recovered, 10);
+        let probe = engine.listen(latest).wait();
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        engine.start().wait()?;
+        for value in 0..3 {
+            emitter.send(value)?;
+            probe.recv()?;
+        }
+        assert!(matches!(
+            engine.snapshot().wait(),
+            Err(SnapshotError::Unpersistable(_))
+        ));
+    }
+
+    #[case]
+    pub fn unnamed_inputs_are_not_persisted() {
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        engine.start().wait()?;
+        emitter.send(42)?;
+        assert_eq!(engine.snapshot().wait(), Err(SnapshotError::UnnamedInput));
+    }
+
+    #[case]
+    pub fn restore_rejects_mismatched_types() {
+        let mut bytes = b"RRKS".to_vec();
+        bytes.extend(1_u32.to_le_bytes());
+        bytes.extend(3_u64.to_le_bytes());
+        // Two string inputs and an add over them, all without values.
+        for name in [
+            "restore_rejects_mismatched_types/left",
+            "restore_rejects_mismatched_types/right",
+        ] {
+            bytes.push(0);
+            bytes.extend((name.len() as u64).to_le_bytes());
+            bytes.extend(name.as_bytes());
+            bytes.push(1);
+        }
+        bytes.push(1);
+        bytes.extend(0_u64.to_le_bytes());
+        bytes.extend(1_u64.to_le_bytes());
+        bytes.push(0);
+        bytes.extend([0; 3]);
+        assert!(matches!(
+            T::restore(&bytes),
+            Err(SnapshotError::TypeMismatch(_))
+        ));
+    }
+
+    #[case]
+    pub fn restore_rejects_garbage() {
+        assert!(T::restore(b"definitely not a snapshot").is_err());
+        let listener = engine.listen(signal).wait();
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        engine.start().immediate()?;
+        emitter.send(42)?;
+        assert_eq!(listener.recv()?, 42);
+    }
+}
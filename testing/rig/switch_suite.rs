@@ -0,0 +1,91 @@
+use rig_macros::test_suite;
+
+#[test_suite]
+pub mod switch {
+
+    use engine_base::{
+        operators::{add, input, optional, switch, Signal},
+        waiting::Waiting,
+        Emit, Engine,
+    };
+
+    #[setup]
+    fn setup<T: Engine>(e: T) {
+        let engine = e;
+        let (selector_ref, selector) = input::<Signal<u64>>();
+        let (a_ref, a) = input::<u64>();
+    }
+
+    #[case]
+    pub fn switch_follows_the_selected_signal() {
+        let (b_ref, b) = input::<u64>();
+        let listener = engine.listen(switch(selector)).wait();
+        let a_probe = engine.listen(a.clone()).wait();
+        let b_probe = engine.listen(b.clone()).wait();
+        let selector_emitter = engine.emit::<Signal<u64>>(selector_ref).wait();
+        let a_emitter = engine.emit::<u64>(a_ref).wait();
+        let b_emitter = engine.emit::<u64>(b_ref).wait();
+        engine.start().wait()?;
+        a_emitter.send(1)?;
+        a_probe.recv()?;
+        selector_emitter.send(a)?;
+        assert_eq!(listener.recv()?, 1);
+        b_emitter.send(5)?;
+        b_probe.recv()?;
+        a_emitter.send(2)?;
+        assert_eq!(listener.recv()?, 2);
+        selector_emitter.send(b)?;
+        assert_eq!(listener.recv()?, 5);
+        a_emitter.send(3)?;
+        a_probe.recv()?;
+        a_probe.recv()?;
+        b_emitter.send(6)?;
+        assert_eq!(listener.recv()?, 6);
+    }
+
+    #[case]
+    pub fn switch_registers_selected_signals() {
+        let listener = engine.listen(switch(selector)).wait();
+        let probe = engine.listen(a.clone()).wait();
+        let selector_emitter = engine.emit::<Signal<u64>>(selector_ref).wait();
+        let a_emitter = engine.emit::<u64>(a_ref).wait();
+        engine.start().wait()?;
+        a_emitter.send(2)?;
+        probe.recv()?;
+        selector_emitter.send(add(a.clone(), a))?;
+        assert_eq!(listener.recv()?, 4);
+        a_emitter.send(3)?;
+        assert_eq!(listener.recv()?, 6);
+    }
+
+    #[case]
+    pub fn switch_moves_above_deeper_signals() {
+        let deep = add(add(add(a.clone(), a.clone()), a.clone()), a.clone());
+        let listener = engine.listen(add(switch(selector), a)).wait();
+        let selector_emitter = engine.emit::<Signal<u64>>(selector_ref).wait();
+        let a_emitter = engine.emit::<u64>(a_ref).wait();
+        engine.start().wait()?;
+        selector_emitter.send(deep)?;
+        assert_eq!(listener.recv()?, 0);
+        a_emitter.send(1)?;
+        a_emitter.send(2)?;
+        assert_eq!(listener.recv()?, 5);
+        assert_eq!(listener.recv()?, 10);
+    }
+
+    #[case]
+    pub fn switch_cannot_follow_itself() {
+        let switched = switch(selector.clone());
+        let listener = engine.listen(optional(switched.clone())).wait();
+        let probe = engine.listen(a.clone()).wait();
+        let selector_emitter = engine.emit::<Signal<u64>>(selector_ref).wait();
+        let a_emitter = engine.emit::<u64>(a_ref).wait();
+        engine.start().wait()?;
+        a_emitter.send(4)?;
+        probe.recv()?;
+        selector_emitter.send(a.clone())?;
+        assert_eq!(listener.recv()?, Some(4));
+        selector_emitter.send(add(switched, a))?;
+        assert_eq!(listener.recv()?, None);
+    }
+}
@@ -0,0 +1,147 @@
+use rig_macros::test_suite;
+
+// Expects the engine to read the time from the manual clock it is paired with.
+#[test_suite]
+pub mod windows {
+
+    use std::{sync::Arc, time::Duration};
+
+    use engine_base::{
+        clock::ManualClock,
+        operators::{
+            collection::Change, count, input, input_named, last_n, window::Window, window_avg,
+            window_count, window_sum,
+        },
+        waiting::Waiting,
+        Emit, Engine, ListenOptions,
+    };
+
+    const REPLAY: ListenOptions = ListenOptions {
+        replay_current: true,
+    };
+
+    #[setup]
+    fn setup<T: Engine>(e: (T, Arc<ManualClock>)) {
+        let (engine, clock) = e;
+        let (input_ref, signal) = input::<u64>();
+    }
+
+    #[case]
+    pub fn lookback_sum_drops_old_updates() {
+        let listener = engine
+            .listen_with(window_sum(signal, Duration::from_secs(10)), REPLAY)
+            .wait();
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        engine.start().wait()?;
+        assert_eq!(listener.recv()?, 0);
+        emitter.send(1)?;
+        assert_eq!(listener.recv()?, 1);
+        clock.advance(Duration::from_secs(5));
+        emitter.send(2)?;
+        assert_eq!(listener.recv()?, 3);
+        clock.advance(Duration::from_secs(7));
+        emitter.send(4)?;
+        assert_eq!(listener.recv()?, 6);
+    }
+
+    #[case]
+    pub fn period_count_starts_over_every_period() {
+        let window = Window::Period(Duration::from_secs(10));
+        let listener = engine.listen(window_count(signal, window)).wait();
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        engine.start().wait()?;
+        emitter.send(1)?;
+        assert_eq!(listener.recv()?, 1);
+        clock.advance(Duration::from_secs(9));
+        emitter.send(1)?;
+        assert_eq!(listener.recv()?, 2);
+        clock.advance(Duration::from_secs(1));
+        emitter.send(1)?;
+        assert_eq!(listener.recv()?, 1);
+    }
+
+    #[case]
+    pub fn zero_lookback_keeps_the_latest_update() {
+        let listener = engine.listen(window_sum(signal, Duration::ZERO)).wait();
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        engine.start().wait()?;
+        emitter.send(3)?;
+        assert_eq!(listener.recv()?, 3);
+        clock.advance(Duration::from_secs(1));
+        emitter.send(4)?;
+        assert_eq!(listener.recv()?, 4);
+    }
+
+    #[case]
+    pub fn avg_rounds_down() {
+        let listener = engine
+            .listen(window_avg(signal, Duration::from_secs(10)))
+            .wait();
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        engine.start().wait()?;
+        emitter.send(2)?;
+        assert_eq!(listener.recv()?, 2);
+        emitter.send(5)?;
+        assert_eq!(listener.recv()?, 3);
+        clock.advance(Duration::from_secs(10));
+        emitter.send(9)?;
+        assert_eq!(listener.recv()?, 9);
+    }
+
+    #[case]
+    pub fn avg_does_not_wrap_around() {
+        let listener = engine
+            .listen(window_avg(signal, Duration::from_secs(10)))
+            .wait();
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        engine.start().wait()?;
+        emitter.send(u64::MAX)?;
+        assert_eq!(listener.recv()?, u64::MAX);
+        emitter.send(u64::MAX - 2)?;
+        assert_eq!(listener.recv()?, u64::MAX - 1);
+    }
+
+    #[case]
+    pub fn last_n_counts_updates_rather_than_time() {
+        let latest = last_n(signal, 2);
+        let listener = engine.listen(latest.clone()).wait();
+        let counted = engine.listen(count(latest)).wait();
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        engine.start().wait()?;
+        emitter.send(10)?;
+        clock.advance(Duration::from_secs(3600));
+        emitter.send(20)?;
+        emitter.send(30)?;
+        assert_eq!(listener.recv()?.into_changes(), vec![Change::Insert(0, 10)]);
+        assert_eq!(listener.recv()?.into_changes(), vec![Change::Insert(1, 20)]);
+        assert_eq!(
+            listener.recv()?.into_changes(),
+            vec![Change::Insert(2, 30), Change::Remove(0)]
+        );
+        assert_eq!(counted.recv()?, 1);
+        assert_eq!(counted.recv()?, 2);
+        assert_eq!(counted.recv()?, 2);
+    }
+
+    #[case]
+    pub fn window_histories_survive_restore() {
+        let (input_ref, signal) = input_named::<u64>("window_histories_survive_restore")?;
+        let summed = window_sum(signal.clone(), Duration::from_secs(10));
+        let latest = last_n(signal, 2);
+        let probe = engine.listen(summed).wait();
+        let _latest = engine.listen(latest).wait();
+        let emitter = engine.emit::<u64>(input_ref).wait();
+        engine.start().wait()?;
+        for value in [1, 2, 3] {
+            clock.advance(Duration::from_secs(1));
+            emitter.send(value)?;
+        }
+        assert_eq!(probe.recv()?, 1);
+        assert_eq!(probe.recv()?, 3);
+        assert_eq!(probe.recv()?, 6);
+        let bytes = engine.snapshot().wait()?;
+
+        let restored = T::restore(&bytes)?;
+        assert_eq!(restored.snapshot().wait()?, bytes);
+    }
+}
@@ -29,6 +29,33 @@ gen_testfile = rule(
     },
 )
 
+def _gen_benchfile_impl(ctx):
+    output = ctx.actions.declare_file(ctx.label.name + ".rs")
+    ctx.actions.expand_template(
+        template = ctx.file._template,
+        output = output,
+        substitutions = {
+            "{cons}": ctx.attr.constructor,
+            "{engine}": ctx.attr.engine,
+        },
+    )
+    return [
+        DefaultInfo(files = depset([output])),
+    ]
+
+gen_benchfile = rule(
+    implementation = _gen_benchfile_impl,
+    attrs = {
+        "constructor": attr.string(mandatory=True),
+        "engine": attr.string(mandatory=True),
+        "_template": attr.label(
+            allow_single_file = True,
+            default = Label(":bench_template.txt"),
+            executable = False,
+        ),
+    },
+)
+
 def suite_run(name, deps, constructor, suite, timeout = 60):
     gen_testfile(
         name = name + "_testfile",
@@ -42,3 +69,27 @@ def suite_run(name, deps, constructor, suite, timeout = 60):
         srcs = [":" + name + "_testfile"],
         deps = deps,
     )
+
+def differential_run(name, deps, reference, candidate, timeout = 60):
+    suite_run(
+        name = name,
+        deps = deps,
+        constructor = "rig::differential::Differential::new({}, {})".format(reference, candidate),
+        suite = "rig::differential_suite",
+        timeout = timeout,
+    )
+
+# Prints one JSON line per measurement, labelled with the target name.
+def bench_run(name, deps, constructor):
+    gen_benchfile(
+        name = name + "_benchfile",
+        constructor = constructor,
+        engine = name,
+    )
+
+    rust_binary(
+        name = name,
+        srcs = [":" + name + "_benchfile"],
+        deps = deps,
+        rustc_flags = ["-Copt-level=3"],
+    )